@@ -25,12 +25,13 @@ fn main() {
     // Initialize the logger
     rudra::log::setup_logging(rudra::log::Verbosity::Normal).expect("Rudra failed to initialize");
 
-    // Initialize the report logger
-    let _report_logger = rudra::report::init_report_logger(rudra::report::default_report_logger());
-
     // Parse the command-line
     let options = CliOpts::parse();
 
+    // Initialize the report logger
+    let _report_logger =
+        rudra::report::init_report_logger(rudra::report::default_report_logger(options.color));
+
     // Deserialize the .ullbc file
     let crate_data: TranslatedCrate = {
         use serde::Deserialize;
@@ -48,6 +49,47 @@ fn main() {
             .translated
     };
 
-    let config = crate::rudra::lib::RudraConfig::default();
+    if let Some(path) = &options.inventory {
+        let mut file = File::create(path)
+            .with_context(|| format!("Failed to create inventory file {}", path))
+            .unwrap();
+        crate::rudra::lib::inventory(crate_data, options.inventory_format, &mut file)
+            .with_context(|| format!("Failed to write inventory file {}", path))
+            .unwrap();
+        return;
+    }
+
+    if let Some(pattern) = &options.explain {
+        let count = crate::rudra::lib::explain(
+            crate_data,
+            pattern,
+            options.unsafe_only,
+            &mut std::io::stdout(),
+        )
+        .context("Failed to write the explanation")
+        .unwrap();
+        if count == 0 {
+            eprintln!("No function with a body matches `{}`", pattern);
+        }
+        return;
+    }
+
+    if let Some(pattern) = &options.dot {
+        let mut file = File::create(&options.dot_output)
+            .with_context(|| format!("Failed to create dot file {}", &options.dot_output))
+            .unwrap();
+        let count = crate::rudra::lib::dot(crate_data, pattern, options.unsafe_only, &mut file)
+            .with_context(|| format!("Failed to write dot file {}", &options.dot_output))
+            .unwrap();
+        if count == 0 {
+            eprintln!("No function with a body matches `{}`", pattern);
+        }
+        return;
+    }
+
+    let config = crate::rudra::lib::RudraConfig {
+        unsafe_only: options.unsafe_only,
+        ..Default::default()
+    };
     crate::rudra::lib::analyze(crate_data, config);
 }
@@ -1,8 +1,34 @@
 use clap::Parser;
 
+use crate::rudra::inventory::InventoryFormat;
+use crate::rudra::report::ColorMode;
+
 #[derive(Debug, Default, Clone, Parser)]
 #[clap(name = "CharonRudra")]
 pub struct CliOpts {
     #[clap(long = "file")]
     pub file: String,
+    /// Only analyze the functions which contain unsafe code
+    #[clap(long = "unsafe-only")]
+    pub unsafe_only: bool,
+    /// Write an inventory of the unsafe operations of each function to this
+    /// file, instead of running the analyses
+    #[clap(long = "inventory")]
+    pub inventory: Option<String>,
+    #[clap(long = "inventory-format", value_enum, default_value = "json")]
+    pub inventory_format: InventoryFormat,
+    /// Write the CFG of the functions matching this name pattern (e.g.
+    /// `my_crate::module::function`) as a Graphviz file, instead of running the analyses
+    #[clap(long = "dot")]
+    pub dot: Option<String>,
+    #[clap(long = "dot-output", default_value = "rudra.dot")]
+    pub dot_output: String,
+    /// Print the body of the functions matching this name pattern and the
+    /// decisions of the unsafe dataflow analysis, instead of running the analyses
+    #[clap(long = "explain")]
+    pub explain: Option<String>,
+    /// Color the source snippets of the reports. Without color, the labeled
+    /// spans are marked with underlines.
+    #[clap(long = "color", value_enum, default_value = "auto")]
+    pub color: ColorMode,
 }
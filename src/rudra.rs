@@ -1,10 +1,16 @@
 pub mod analysis;
+pub mod call_graph;
 pub mod context;
+pub mod dot;
+pub mod explain;
 pub mod graph;
+pub mod inventory;
 pub mod iter;
 pub mod lib;
+pub mod local_defs;
 pub mod log;
 pub mod macros;
 pub mod paths;
 pub mod report;
 pub mod utils;
+pub mod visitor;
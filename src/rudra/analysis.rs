@@ -1,5 +1,13 @@
+mod double_free;
+mod drop_panic;
+mod from_raw_parts;
+mod mut_aliasing;
+mod ptr_cast;
 mod send_sync_variance;
+mod transmute;
+mod uninit_exposure;
 mod unsafe_dataflow;
+mod unsafe_impl;
 //mod unsafe_destructor;
 
 use snafu::{Error, ErrorCompat};
@@ -8,10 +16,29 @@ use tracing::{debug, error, info};
 use crate::logger::*;
 use crate::rudra::report::ReportLevel;
 
+use charon_lib::ullbc_ast::{Literal, Operand, RawConstantExpr, ScalarValue};
+
+pub use double_free::BehaviorFlag as DoubleFreeBehaviorFlag;
+pub use double_free::DoubleFreeChecker;
+pub use drop_panic::BehaviorFlag as DropPanicBehaviorFlag;
+pub use drop_panic::DropPanicChecker;
+pub use from_raw_parts::BehaviorFlag as FromRawPartsBehaviorFlag;
+pub use from_raw_parts::FromRawPartsChecker;
+pub use mut_aliasing::BehaviorFlag as MutAliasingBehaviorFlag;
+pub use mut_aliasing::MutAliasingChecker;
+pub use ptr_cast::BehaviorFlag as PtrCastBehaviorFlag;
+pub use ptr_cast::PtrCastChecker;
 pub use send_sync_variance::BehaviorFlag as SendSyncBehaviorFlag;
 //pub use send_sync_variance::{SendSyncVarianceChecker};
+pub use transmute::BehaviorFlag as TransmuteBehaviorFlag;
+pub use transmute::TransmuteChecker;
+pub use uninit_exposure::BehaviorFlag as UninitExposureBehaviorFlag;
+pub use uninit_exposure::UninitExposureChecker;
 pub use unsafe_dataflow::BehaviorFlag as UnsafeDataflowBehaviorFlag;
+pub use unsafe_dataflow::DataflowExplanation;
 pub use unsafe_dataflow::UnsafeDataflowChecker;
+pub use unsafe_impl::BehaviorFlag as UnsafeImplBehaviorFlag;
+pub use unsafe_impl::UnsafeImplChecker;
 //pub use unsafe_destructor::UnsafeDestructorChecker;
 
 pub type AnalysisResult<'tcx, T> = Result<T, Box<dyn AnalysisError + 'tcx>>;
@@ -69,12 +96,32 @@ pub enum AnalysisKind {
     UnsafeDestructor,
     SendSyncVariance(SendSyncBehaviorFlag),
     UnsafeDataflow(UnsafeDataflowBehaviorFlag),
+    UninitExposure(UninitExposureBehaviorFlag),
+    DoubleFree(DoubleFreeBehaviorFlag),
+    MutAliasing(MutAliasingBehaviorFlag),
+    Transmute(TransmuteBehaviorFlag),
+    FromRawParts(FromRawPartsBehaviorFlag),
+    PtrCast(PtrCastBehaviorFlag),
+    UnsafeImpl(UnsafeImplBehaviorFlag),
+    DropPanic(DropPanicBehaviorFlag),
 }
 
 trait IntoReportLevel {
     fn report_level(&self) -> ReportLevel;
 }
 
+/// Check if the argument of `Vec::set_len()` is 0_usize: `vec.set_len(0)`
+/// leaks the elements and doesn't expose anything.
+fn vec_set_len_to_0(args: &[Operand]) -> bool {
+    args.iter().any(|arg| {
+        matches!(
+            arg,
+            Operand::Const(x)
+                if matches!(x.value, RawConstantExpr::Literal(Literal::Scalar(ScalarValue::Usize(0))))
+        )
+    })
+}
+
 impl Into<Cow<'static, str>> for AnalysisKind {
     fn into(self) -> Cow<'static, str> {
         match &self {
@@ -133,6 +180,119 @@ impl Into<Cow<'static, str>> for AnalysisKind {
                 if bypass_kinds.contains(UnsafeDataflowBehaviorFlag::VEC_SET_LEN) {
                     v.push("VecSetLen")
                 }
+                if bypass_kinds.contains(UnsafeDataflowBehaviorFlag::USER_CLOSURE) {
+                    v.push("UserClosure")
+                }
+                v.join("/").into()
+            }
+            AnalysisKind::UninitExposure(uninit_kinds) => {
+                let mut v = vec!["UninitExposure:"];
+                if uninit_kinds.contains(UninitExposureBehaviorFlag::VEC_SET_LEN) {
+                    v.push("VecSetLen")
+                }
+                if uninit_kinds.contains(UninitExposureBehaviorFlag::ASSUME_INIT) {
+                    v.push("AssumeInit")
+                }
+                if uninit_kinds.contains(UninitExposureBehaviorFlag::MEM_UNINITIALIZED) {
+                    v.push("MemUninitialized")
+                }
+                if uninit_kinds.contains(UninitExposureBehaviorFlag::MEM_ZEROED) {
+                    v.push("MemZeroed")
+                }
+                v.join("/").into()
+            }
+            AnalysisKind::DoubleFree(double_free_kinds) => {
+                let mut v = vec!["DoubleFree:"];
+                if double_free_kinds.contains(DoubleFreeBehaviorFlag::READ_FROM_REF) {
+                    v.push("ReadFromRef")
+                }
+                if double_free_kinds.contains(DoubleFreeBehaviorFlag::READ_FROM_LOCAL) {
+                    v.push("ReadFromLocal")
+                }
+                v.join("/").into()
+            }
+            AnalysisKind::MutAliasing(aliasing_kinds) => {
+                let mut v = vec!["MutAliasing:"];
+                if aliasing_kinds.contains(MutAliasingBehaviorFlag::ALIASING_MUT) {
+                    v.push("AliasingMut")
+                }
+                if aliasing_kinds.contains(MutAliasingBehaviorFlag::MUT_FROM_SHARED) {
+                    v.push("MutFromShared")
+                }
+                v.join("/").into()
+            }
+            AnalysisKind::Transmute(transmute_kinds) => {
+                let mut v = vec!["Transmute:"];
+                if transmute_kinds.contains(TransmuteBehaviorFlag::REF_TO_MUT) {
+                    v.push("RefToMut")
+                }
+                if transmute_kinds.contains(TransmuteBehaviorFlag::LIFETIME_EXTENSION) {
+                    v.push("LifetimeExtension")
+                }
+                if transmute_kinds.contains(TransmuteBehaviorFlag::INT_TO_REF) {
+                    v.push("IntToRef")
+                }
+                if transmute_kinds.contains(TransmuteBehaviorFlag::INVALID_BIT_PATTERN) {
+                    v.push("InvalidBitPattern")
+                }
+                v.join("/").into()
+            }
+            AnalysisKind::FromRawParts(mismatch_kinds) => {
+                let mut v = vec!["FromRawParts:"];
+                if mismatch_kinds.contains(FromRawPartsBehaviorFlag::NOT_HEAP_ALLOCATED) {
+                    v.push("NotHeapAllocated")
+                }
+                if mismatch_kinds.contains(FromRawPartsBehaviorFlag::ELEMENT_TYPE_MISMATCH) {
+                    v.push("ElementTypeMismatch")
+                }
+                if mismatch_kinds.contains(FromRawPartsBehaviorFlag::CAPACITY_MISMATCH) {
+                    v.push("CapacityMismatch")
+                }
+                v.join("/").into()
+            }
+            AnalysisKind::PtrCast(cast_kinds) => {
+                let mut v = vec!["PtrCast:"];
+                if cast_kinds.contains(PtrCastBehaviorFlag::ALIGNMENT_INCREASE) {
+                    v.push("AlignmentIncrease")
+                }
+                if cast_kinds.contains(PtrCastBehaviorFlag::SIZE_INCREASE) {
+                    v.push("SizeIncrease")
+                }
+                v.join("/").into()
+            }
+            AnalysisKind::UnsafeImpl(impl_kinds) => {
+                let mut v = vec!["UnsafeImpl:"];
+                if impl_kinds.contains(UnsafeImplBehaviorFlag::SEND_IMPL) {
+                    v.push("Send")
+                }
+                if impl_kinds.contains(UnsafeImplBehaviorFlag::SYNC_IMPL) {
+                    v.push("Sync")
+                }
+                if impl_kinds.contains(UnsafeImplBehaviorFlag::UNPIN_IMPL) {
+                    v.push("Unpin")
+                }
+                if impl_kinds.contains(UnsafeImplBehaviorFlag::GLOBAL_ALLOC_IMPL) {
+                    v.push("GlobalAlloc")
+                }
+                if impl_kinds.contains(UnsafeImplBehaviorFlag::UNSAFE_TRAIT_IMPL) {
+                    v.push("UnsafeTrait")
+                }
+                if impl_kinds.contains(UnsafeImplBehaviorFlag::MISSING_BOUND) {
+                    v.push("MissingBound")
+                }
+                v.join("/").into()
+            }
+            AnalysisKind::DropPanic(panic_kinds) => {
+                let mut v = vec!["DropPanic:"];
+                if panic_kinds.contains(DropPanicBehaviorFlag::PANIC_CALL) {
+                    v.push("PanicCall")
+                }
+                if panic_kinds.contains(DropPanicBehaviorFlag::ASSERT) {
+                    v.push("Assert")
+                }
+                if panic_kinds.contains(DropPanicBehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN) {
+                    v.push("GenericCallAfterTeardown")
+                }
                 v.join("/").into()
             }
         }
@@ -0,0 +1,360 @@
+//! Double free detector: a value duplicated with `ptr::read` is dropped while
+//! the place it was read from still owns it, e.g. reading out of a `&mut T`
+//! and dropping the result without writing a value back.
+
+use bitflags::bitflags;
+use if_chain::if_chain;
+use termcolor::Color;
+
+use crate::rudra::analysis::{AnalysisKind, IntoReportLevel};
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::graph::TaintAnalyzer;
+use crate::rudra::local_defs::{self, LocalDefs};
+use crate::rudra::paths;
+use crate::rudra::report::{rudra_report, Report, ReportLevel};
+use crate::rudra::utils::{self, LabelKind};
+
+use charon_lib::ast::meta::Span;
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::types::{GenericParams, TyKind};
+use charon_lib::ullbc_ast::{
+    BlockId, BodyContents, Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, Locals,
+    RawStatement, VarId,
+};
+
+#[derive(Clone, Copy)]
+pub struct DoubleFreeChecker<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+}
+
+impl<'tcx> DoubleFreeChecker<'tcx> {
+    pub fn new(rcx: RudraCtxt<'tcx>) -> Self {
+        DoubleFreeChecker { rcx }
+    }
+
+    pub fn analyze(self) {
+        // Iterate over all functions
+        for decl in self.rcx.crate_data.fun_decls.iter() {
+            if let Some(status) = inner::DoubleFreeBodyAnalyzer::analyze_body(self.rcx, decl) {
+                let behavior_flag = status.behavior_flag();
+                let exposure = self.rcx.api_exposure(decl.def_id);
+                let report_level = exposure.adjust(behavior_flag.report_level());
+                if !behavior_flag.is_empty() && report_level >= self.rcx.report_level() {
+                    let mut color_span = if let Some(span) =
+                        utils::ColorSpan::new(&self.rcx.crate_data, decl.item_meta.span)
+                    {
+                        span
+                    } else {
+                        continue;
+                    };
+
+                    for &span in status.read_spans() {
+                        color_span.add_label(
+                            LabelKind::Secondary,
+                            Color::Yellow,
+                            span,
+                            "value duplicated with `ptr::read`",
+                        );
+                    }
+
+                    for &span in status.drop_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Red,
+                            span,
+                            "dropped while still owned",
+                        );
+                    }
+
+                    rudra_report(
+                        Report::with_color_span(
+                            report_level,
+                            AnalysisKind::DoubleFree(behavior_flag),
+                            format!(
+                            "Potential double free of a value duplicated with `ptr::read` in `{}`",
+                            self.rcx.crate_data.into_fmt().format_object(decl.def_id)
+                        ),
+                            &color_span,
+                        )
+                        .with_exposure(exposure),
+                    )
+                }
+            }
+        }
+    }
+}
+
+mod inner {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct DoubleFreeStatus {
+        reads: Vec<Span>,
+        drops: Vec<Span>,
+        behavior_flag: BehaviorFlag,
+    }
+
+    impl DoubleFreeStatus {
+        pub fn behavior_flag(&self) -> BehaviorFlag {
+            self.behavior_flag
+        }
+
+        pub fn read_spans(&self) -> &Vec<Span> {
+            &self.reads
+        }
+
+        pub fn drop_spans(&self) -> &Vec<Span> {
+            &self.drops
+        }
+    }
+
+    /// A value duplicated by `ptr::read`
+    struct Duplicate {
+        block_id: BlockId,
+        statement_idx: usize,
+        span: Span,
+        /// The local holding the duplicate
+        dest: VarId,
+        /// The local the pointer passed to `ptr::read` has been derived from
+        origin: VarId,
+    }
+
+    pub struct DoubleFreeBodyAnalyzer<'a, 'tcx> {
+        rcx: RudraCtxt<'tcx>,
+        /// The generic parameters of the analyzed function
+        generics: &'a GenericParams,
+        locals: &'a Locals,
+        body: &'a BodyContents,
+        status: DoubleFreeStatus,
+    }
+
+    impl<'a, 'tcx> DoubleFreeBodyAnalyzer<'a, 'tcx> {
+        fn new(
+            rcx: RudraCtxt<'tcx>,
+            generics: &'a GenericParams,
+            locals: &'a Locals,
+            body: &'a BodyContents,
+        ) -> Self {
+            DoubleFreeBodyAnalyzer {
+                rcx,
+                generics,
+                locals,
+                body,
+                status: Default::default(),
+            }
+        }
+
+        pub fn analyze_body(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> Option<DoubleFreeStatus> {
+            if !rcx.analyzes_body(decl) {
+                return None;
+            }
+            let body_id = *decl.body.as_ref().ok()?;
+            let body = rcx.crate_data.bodies.get(body_id)?.as_unstructured()?;
+            let body_analyzer = DoubleFreeBodyAnalyzer::new(
+                rcx,
+                &decl.signature.generics,
+                &body.locals,
+                &body.body,
+            );
+            Some(body_analyzer.analyze())
+        }
+
+        fn analyze(mut self) -> DoubleFreeStatus {
+            let local_defs = LocalDefs::new(self.body);
+
+            for duplicate in self.duplicates(&local_defs) {
+                let origin_ty = match self.locals.vars.get(duplicate.origin) {
+                    Some(var) => &var.ty,
+                    None => continue,
+                };
+                let flag = match origin_ty.kind() {
+                    // The caller drops the value behind the reference
+                    TyKind::Ref(..) => BehaviorFlag::READ_FROM_REF,
+                    // We can't tell who owns the value behind a raw pointer
+                    TyKind::RawPtr(..) => continue,
+                    _ => BehaviorFlag::READ_FROM_LOCAL,
+                };
+
+                // Writing a value back gives the ownership of the duplicate to the origin
+                let written_back =
+                    |st: &RawStatement| self.writes_back(st, duplicate.origin, &local_defs);
+                let dest_drops = self.reached(
+                    &duplicate,
+                    |st| drops(st, duplicate.dest, true),
+                    |st| local_defs::statement_moves(st, duplicate.dest) || written_back(st),
+                );
+                if dest_drops.is_empty() {
+                    continue;
+                }
+                if flag == BehaviorFlag::READ_FROM_LOCAL {
+                    let origin_drops = self.reached(
+                        &duplicate,
+                        |st| drops(st, duplicate.origin, false),
+                        |st| local_defs::statement_moves(st, duplicate.origin) || written_back(st),
+                    );
+                    if origin_drops.is_empty() {
+                        continue;
+                    }
+                }
+
+                log::trace!(
+                    "Found double free: {:?} read from {:?} (block: {})",
+                    duplicate.dest,
+                    duplicate.origin,
+                    duplicate.block_id
+                );
+                self.status.behavior_flag |= flag;
+                self.status.reads.push(duplicate.span);
+                self.status.drops.extend(dest_drops);
+            }
+
+            self.status
+        }
+
+        /// Collects the non-Copy values duplicated by `ptr::read` into a whole local.
+        fn duplicates(&self, local_defs: &LocalDefs) -> Vec<Duplicate> {
+            let mut duplicates = Vec::new();
+            for (block_id, block) in self.body.iter_indexed() {
+                for (statement_idx, st) in block.statements.iter().enumerate() {
+                    if_chain! {
+                        if let RawStatement::Call(Call {
+                            func:
+                                FnOperand::Regular(FnPtr {
+                                    func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                                    generics,
+                                }),
+                            args,
+                            dest,
+                        }) = &st.content;
+                        if dest.projection.is_empty();
+                        if let Some(decl) = self.rcx.crate_data.fun_decls.get(*callee_did);
+                        if paths::PTR_READ_LIST.contains(self.rcx, &decl.item_meta.name).is_some();
+                        // Copy values have no drop glue
+                        if !generics
+                            .types
+                            .get(0.into())
+                            .map_or(true, |ty| self.rcx.is_copyable(ty, self.generics));
+                        if let Some(origin) = args.first().and_then(|arg| local_defs.operand_address_root(arg));
+                        if origin != dest.var_id;
+                        then {
+                            duplicates.push(Duplicate {
+                                block_id,
+                                statement_idx,
+                                span: st.span,
+                                dest: dest.var_id,
+                                origin,
+                            });
+                        }
+                    }
+                }
+            }
+            duplicates
+        }
+
+        /// Returns true if `st` is a `ptr::write` to a pointer derived from `origin`.
+        fn writes_back(&self, st: &RawStatement, origin: VarId, local_defs: &LocalDefs) -> bool {
+            if_chain! {
+                if let RawStatement::Call(Call {
+                    func:
+                        FnOperand::Regular(FnPtr {
+                            func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                            ..
+                        }),
+                    args,
+                    ..
+                }) = st;
+                if let Some(decl) = self.rcx.crate_data.fun_decls.get(*callee_did);
+                if paths::PTR_WRITE_LIST.contains(self.rcx, &decl.item_meta.name).is_some();
+                then {
+                    args.first().and_then(|arg| local_defs.operand_address_root(arg)) == Some(origin)
+                } else {
+                    false
+                }
+            }
+        }
+
+        /// Returns the spans of the statements matching `is_sink` which can be
+        /// executed after the duplication without going through a statement
+        /// matching `is_kill`. The statements preceding the duplication in its
+        /// own block are ignored.
+        fn reached(
+            &self,
+            duplicate: &Duplicate,
+            is_sink: impl Fn(&RawStatement) -> bool,
+            is_kill: impl Fn(&RawStatement) -> bool,
+        ) -> Vec<Span> {
+            // The rest of the block of the duplication
+            let statements = &self.body[duplicate.block_id].statements;
+            for st in &statements[duplicate.statement_idx + 1..] {
+                if is_sink(&st.content) {
+                    return vec![st.span];
+                }
+                if is_kill(&st.content) {
+                    return Vec::new();
+                }
+            }
+
+            let mut taint_analyzer = TaintAnalyzer::new(self.body);
+            taint_analyzer.mark_source(duplicate.block_id.index(), &BehaviorFlag::all());
+            // The sinks, with their node and their index in the node
+            let mut sinks = Vec::new();
+            for (id, block) in self.body.iter_indexed() {
+                if id == duplicate.block_id {
+                    continue;
+                }
+                // Only the first relevant statement of a block matters
+                let first = block
+                    .statements
+                    .iter()
+                    .find(|st| is_sink(&st.content) || is_kill(&st.content));
+                match first {
+                    Some(st) if is_sink(&st.content) => {
+                        let empty = BehaviorFlag::empty();
+                        let idx = taint_analyzer.mark_sink_at(id.index(), &empty, &empty);
+                        sinks.push((st.span, id.index(), idx));
+                    }
+                    Some(_) => taint_analyzer.mark_kill(id.index(), &BehaviorFlag::all()),
+                    None => (),
+                }
+            }
+
+            let view_taints = taint_analyzer.sink_view_taints();
+            sinks
+                .into_iter()
+                .filter(|(_, node, idx)| !view_taints[*node][*idx].is_empty())
+                .map(|(span, _, _)| span)
+                .collect()
+        }
+    }
+
+    /// Returns true if `st` drops the local, or a part of it if `whole` is false.
+    fn drops(st: &RawStatement, var_id: VarId, whole: bool) -> bool {
+        matches!(
+            st,
+            RawStatement::Drop(place)
+                if place.var_id == var_id && (!whole || place.projection.is_empty())
+        )
+    }
+}
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BehaviorFlag: u8 {
+        // The duplicate is dropped while the caller still owns the value behind the reference
+        const READ_FROM_REF = 0b00000001;
+        // The duplicate and the local it was read from are both dropped
+        const READ_FROM_LOCAL = 0b00000010;
+    }
+}
+
+impl IntoReportLevel for BehaviorFlag {
+    fn report_level(&self) -> ReportLevel {
+        let high = BehaviorFlag::READ_FROM_REF | BehaviorFlag::READ_FROM_LOCAL;
+
+        if !(*self & high).is_empty() {
+            ReportLevel::Error
+        } else {
+            ReportLevel::Info
+        }
+    }
+}
@@ -0,0 +1,336 @@
+//! Destructor hazard checker, on the `Drop::drop` bodies of the local types:
+//! - a panic or a failing assertion reachable in the destructor, which aborts
+//!   the process if the value is dropped during unwinding,
+//! - a call to unresolved generic code after a field was moved out of `self`
+//!   (e.g. with `ptr::read`): if the generic code panics, the field is dropped
+//!   twice while unwinding.
+
+use bitflags::bitflags;
+use termcolor::Color;
+
+use crate::rudra::analysis::{AnalysisKind, IntoReportLevel};
+use crate::rudra::context::{generics_have_unresolved, RudraCtxt};
+use crate::rudra::graph::{Graph, TaintAnalyzer};
+use crate::rudra::iter::LocalTraitIter;
+use crate::rudra::local_defs::LocalDefs;
+use crate::rudra::paths;
+use crate::rudra::report::{rudra_report, Report, ReportLevel};
+use crate::rudra::utils::{self, LabelKind};
+
+use charon_lib::ast::meta::Span;
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::ullbc_ast::{
+    AbortKind, BodyContents, Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef,
+    RawStatement, RawTerminator, VarId,
+};
+
+#[derive(Clone, Copy)]
+pub struct DropPanicChecker<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+}
+
+impl<'tcx> DropPanicChecker<'tcx> {
+    pub fn new(rcx: RudraCtxt<'tcx>) -> Self {
+        DropPanicChecker { rcx }
+    }
+
+    pub fn analyze(self) {
+        let drop_trait = self.rcx.crate_data.trait_decls.iter().find(|tdecl| {
+            paths::DROP_TRAIT_LIST
+                .contains(self.rcx, &tdecl.item_meta.name)
+                .is_some()
+        });
+        let drop_trait = match drop_trait {
+            Some(tdecl) => tdecl.def_id,
+            None => return,
+        };
+
+        let mut drop_fns = Vec::new();
+        for impl_id in LocalTraitIter::new(self.rcx, drop_trait) {
+            let timpl = match self.rcx.crate_data.trait_impls.get(impl_id) {
+                Some(timpl) if timpl.item_meta.is_local => timpl,
+                _ => continue,
+            };
+            drop_fns.extend(
+                timpl
+                    .required_methods
+                    .iter()
+                    .filter(|(item_name, _)| item_name.0 == "drop")
+                    .map(|(_, fun_id)| *fun_id),
+            );
+        }
+        // The impls of a trait are not ordered
+        drop_fns.sort();
+
+        for fun_id in drop_fns {
+            let decl = match self.rcx.crate_data.fun_decls.get(fun_id) {
+                Some(decl) => decl,
+                None => continue,
+            };
+            if let Some(status) = inner::DropPanicBodyAnalyzer::analyze_body(self.rcx, decl) {
+                let behavior_flag = status.behavior_flag();
+                let exposure = self.rcx.api_exposure(decl.def_id);
+                let report_level = exposure.adjust(behavior_flag.report_level());
+                if !behavior_flag.is_empty() && report_level >= self.rcx.report_level() {
+                    let mut color_span = if let Some(span) =
+                        utils::ColorSpan::new(&self.rcx.crate_data, decl.item_meta.span)
+                    {
+                        span
+                    } else {
+                        continue;
+                    };
+
+                    for &span in status.panic_spans() {
+                        color_span.add_label(LabelKind::Primary, Color::Red, span, "may panic");
+                    }
+
+                    for &span in status.generic_call_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Red,
+                            span,
+                            "generic call after teardown",
+                        );
+                    }
+
+                    for &span in status.teardown_spans() {
+                        color_span.add_label(
+                            LabelKind::Secondary,
+                            Color::Yellow,
+                            span,
+                            "field moved out of `self`",
+                        );
+                    }
+
+                    rudra_report(
+                        Report::with_color_span(
+                            report_level,
+                            AnalysisKind::DropPanic(behavior_flag),
+                            format!(
+                                "Potential panic in the destructor `{}`",
+                                self.rcx.crate_data.into_fmt().format_object(decl.def_id)
+                            ),
+                            &color_span,
+                        )
+                        .with_exposure(exposure),
+                    )
+                }
+            }
+        }
+    }
+}
+
+mod inner {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct DropPanicStatus {
+        panics: Vec<Span>,
+        generic_calls: Vec<Span>,
+        teardowns: Vec<Span>,
+        behavior_flag: BehaviorFlag,
+    }
+
+    impl DropPanicStatus {
+        pub fn behavior_flag(&self) -> BehaviorFlag {
+            self.behavior_flag
+        }
+
+        pub fn panic_spans(&self) -> &Vec<Span> {
+            &self.panics
+        }
+
+        pub fn generic_call_spans(&self) -> &Vec<Span> {
+            &self.generic_calls
+        }
+
+        pub fn teardown_spans(&self) -> &Vec<Span> {
+            &self.teardowns
+        }
+    }
+
+    pub struct DropPanicBodyAnalyzer<'a, 'tcx> {
+        rcx: RudraCtxt<'tcx>,
+        body: &'a BodyContents,
+        status: DropPanicStatus,
+    }
+
+    impl<'a, 'tcx> DropPanicBodyAnalyzer<'a, 'tcx> {
+        fn new(rcx: RudraCtxt<'tcx>, body: &'a BodyContents) -> Self {
+            DropPanicBodyAnalyzer {
+                rcx,
+                body,
+                status: Default::default(),
+            }
+        }
+
+        pub fn analyze_body(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> Option<DropPanicStatus> {
+            if !rcx.analyzes_body(decl) {
+                return None;
+            }
+            let body_id = *decl.body.as_ref().ok()?;
+            let body = rcx.crate_data.bodies.get(body_id)?.as_unstructured()?;
+            let body_analyzer = DropPanicBodyAnalyzer::new(rcx, &body.body);
+            Some(body_analyzer.analyze())
+        }
+
+        fn analyze(mut self) -> DropPanicStatus {
+            // One taint analysis per flag, as the sinks are specific to each flag
+            let mut taint_analyzers = [
+                BehaviorFlag::PANIC_CALL,
+                BehaviorFlag::ASSERT,
+                BehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN,
+            ]
+            .map(|flag| (flag, TaintAnalyzer::new(self.body)));
+            // Calls to generic code found after a teardown in the same block
+            let mut direct_flag = BehaviorFlag::empty();
+            let local_defs = LocalDefs::new(self.body);
+            // The first argument of `drop` is `&mut self`
+            let self_var = VarId::from_usize(1);
+
+            // Panics and assertions are reachable from the entry of the destructor
+            let entry_source = BehaviorFlag::PANIC_CALL | BehaviorFlag::ASSERT;
+            for (_, taint_analyzer) in taint_analyzers.iter_mut() {
+                taint_analyzer.mark_source(0, &entry_source);
+            }
+
+            for (id, block) in self.body.iter_indexed() {
+                let mut block_sink = BehaviorFlag::empty();
+                let mut torn_down = false;
+                for st in &block.statements {
+                    match &st.content {
+                        RawStatement::Assert(..) => {
+                            log::trace!("Found assertion (block: {id})");
+                            block_sink |= BehaviorFlag::ASSERT;
+                            self.status.panics.push(st.span);
+                        }
+                        RawStatement::Call(Call {
+                            func:
+                                FnOperand::Regular(FnPtr {
+                                    func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                                    generics,
+                                }),
+                            args,
+                            ..
+                        }) => {
+                            let name = match self.rcx.crate_data.fun_decls.get(*callee_did) {
+                                Some(decl) => &decl.item_meta.name,
+                                None => continue,
+                            };
+
+                            if paths::PANIC_FN_LIST.contains(self.rcx, name).is_some() {
+                                log::trace!("Found panic call (block: {id})");
+                                block_sink |= BehaviorFlag::PANIC_CALL;
+                                self.status.panics.push(st.span);
+                            } else if paths::TEARDOWN_LIST.contains(self.rcx, name).is_some()
+                                && args
+                                    .first()
+                                    .and_then(|arg| local_defs.operand_root(arg))
+                                    .map_or(false, |root| {
+                                        local_defs.origins(root).contains(&self_var)
+                                    })
+                            {
+                                log::trace!("Found teardown of a field (block: {id})");
+                                torn_down = true;
+                                self.status.teardowns.push(st.span);
+                            } else if paths::GENERIC_FN_LIST.contains(self.rcx, name).is_some()
+                                || generics_have_unresolved(generics)
+                            {
+                                log::trace!("Found unresolvable generic call (block: {id})");
+                                block_sink |= BehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN;
+                                if torn_down {
+                                    direct_flag |= BehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN;
+                                }
+                                self.status.generic_calls.push(st.span);
+                            }
+                        }
+                        RawStatement::Call(Call {
+                            func:
+                                FnOperand::Regular(FnPtr {
+                                    func: FunIdOrTraitMethodRef::Trait(tref, item_name, ..),
+                                    generics,
+                                }),
+                            ..
+                        }) => {
+                            if self
+                                .rcx
+                                .trait_call_may_call_user_code(tref, item_name, generics)
+                            {
+                                log::trace!("Found unresolvable generic call (block: {id})");
+                                block_sink |= BehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN;
+                                if torn_down {
+                                    direct_flag |= BehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN;
+                                }
+                                self.status.generic_calls.push(st.span);
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                if let RawTerminator::Abort(AbortKind::Panic(..)) = &block.terminator.content {
+                    log::trace!("Found panic (block: {id})");
+                    block_sink |= BehaviorFlag::PANIC_CALL;
+                    self.status.panics.push(block.terminator.span);
+                }
+
+                for (flag, taint_analyzer) in taint_analyzers.iter_mut() {
+                    if block_sink.contains(*flag) {
+                        taint_analyzer.mark_sink(id.index());
+                    }
+                }
+                if torn_down {
+                    // The calls preceding the teardown in the block are only
+                    // reachable through a loop: start from the next blocks
+                    for (flag, taint_analyzer) in taint_analyzers
+                        .iter_mut()
+                        .filter(|(flag, _)| *flag == BehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN)
+                    {
+                        for next in self.body.next(id.index()) {
+                            taint_analyzer.mark_source(next, flag);
+                        }
+                    }
+                }
+            }
+
+            self.status.behavior_flag = taint_analyzers
+                .iter()
+                .fold(direct_flag, |acc, (flag, taint_analyzer)| {
+                    acc | (taint_analyzer.propagate() & *flag)
+                });
+            // Generic calls and teardowns are only relevant together
+            if !self
+                .status
+                .behavior_flag
+                .contains(BehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN)
+            {
+                self.status.generic_calls.clear();
+                self.status.teardowns.clear();
+            }
+            self.status
+        }
+    }
+}
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BehaviorFlag: u8 {
+        // A panic call, `unwrap` or `expect` reachable in the destructor
+        const PANIC_CALL = 0b00000001;
+        // An assertion reachable in the destructor
+        const ASSERT = 0b00000010;
+        // A call to unresolved generic code after a field of `self` was moved out
+        const GENERIC_CALL_AFTER_TEARDOWN = 0b00000100;
+    }
+}
+
+impl IntoReportLevel for BehaviorFlag {
+    fn report_level(&self) -> ReportLevel {
+        let med = BehaviorFlag::PANIC_CALL | BehaviorFlag::GENERIC_CALL_AFTER_TEARDOWN;
+
+        if !(*self & med).is_empty() {
+            ReportLevel::Warning
+        } else {
+            ReportLevel::Info
+        }
+    }
+}
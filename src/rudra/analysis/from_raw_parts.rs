@@ -0,0 +1,367 @@
+//! `Vec::from_raw_parts` provenance checker: the pointer must come from a
+//! `Vec` or a `Box` allocated with the same element type and capacity.
+//! Reports pointers to buffers that may not be heap allocated (`as_ptr` of a
+//! slice, borrowed locals), allocations of another element type and
+//! capacities which differ from the allocated one.
+
+use std::collections::HashSet;
+
+use bitflags::bitflags;
+use termcolor::Color;
+
+use crate::rudra::analysis::{AnalysisKind, IntoReportLevel};
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::local_defs::{self, LocalDef, LocalDefs};
+use crate::rudra::paths::{self, PathSet};
+use crate::rudra::report::{rudra_report, Report, ReportLevel};
+use crate::rudra::utils::{self, LabelKind};
+
+use charon_lib::ast::meta::Span;
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::types::{BuiltinTy, Ty, TyKind, TypeId};
+use charon_lib::ullbc_ast::{
+    BodyContents, Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, Literal, Locals,
+    Operand, RawConstantExpr, RawStatement, Rvalue, ScalarValue, UnOp, VarId,
+};
+
+#[derive(Clone, Copy)]
+pub struct FromRawPartsChecker<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+}
+
+impl<'tcx> FromRawPartsChecker<'tcx> {
+    pub fn new(rcx: RudraCtxt<'tcx>) -> Self {
+        FromRawPartsChecker { rcx }
+    }
+
+    pub fn analyze(self) {
+        // Iterate over all functions
+        for decl in self.rcx.crate_data.fun_decls.iter() {
+            if let Some(status) = inner::FromRawPartsBodyAnalyzer::analyze_body(self.rcx, decl) {
+                let behavior_flag = status.behavior_flag();
+                let exposure = self.rcx.api_exposure(decl.def_id);
+                let report_level = exposure.adjust(behavior_flag.report_level());
+                if !behavior_flag.is_empty() && report_level >= self.rcx.report_level() {
+                    let mut color_span = if let Some(span) =
+                        utils::ColorSpan::new(&self.rcx.crate_data, decl.item_meta.span)
+                    {
+                        span
+                    } else {
+                        continue;
+                    };
+
+                    for &span in status.from_raw_parts_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Red,
+                            span,
+                            "`Vec::from_raw_parts`",
+                        );
+                    }
+
+                    rudra_report(
+                        Report::with_color_span(
+                            report_level,
+                            AnalysisKind::FromRawParts(behavior_flag),
+                            format!(
+                                "Potential allocation mismatch in `Vec::from_raw_parts` in `{}`",
+                                self.rcx.crate_data.into_fmt().format_object(decl.def_id)
+                            ),
+                            &color_span,
+                        )
+                        .with_exposure(exposure),
+                    )
+                }
+            }
+        }
+    }
+}
+
+mod inner {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct FromRawPartsStatus {
+        from_raw_parts: Vec<Span>,
+        behavior_flag: BehaviorFlag,
+    }
+
+    impl FromRawPartsStatus {
+        pub fn behavior_flag(&self) -> BehaviorFlag {
+            self.behavior_flag
+        }
+
+        pub fn from_raw_parts_spans(&self) -> &Vec<Span> {
+            &self.from_raw_parts
+        }
+    }
+
+    /// Where the pointer passed to `Vec::from_raw_parts` comes from
+    enum PointerOrigin<'a> {
+        /// The pointer is returned by a function
+        Call(&'a Call),
+        /// The pointer borrows a local
+        Local,
+        Unknown,
+    }
+
+    /// The allocation the pointer points to
+    struct Allocation<'a> {
+        /// The element type of the allocation, if known
+        elem_ty: Option<&'a Ty>,
+        /// The local holding the `Vec` or `Box`, if known
+        owner: Option<VarId>,
+    }
+
+    pub struct FromRawPartsBodyAnalyzer<'a, 'tcx> {
+        rcx: RudraCtxt<'tcx>,
+        locals: &'a Locals,
+        body: &'a BodyContents,
+        status: FromRawPartsStatus,
+    }
+
+    impl<'a, 'tcx> FromRawPartsBodyAnalyzer<'a, 'tcx> {
+        fn new(rcx: RudraCtxt<'tcx>, locals: &'a Locals, body: &'a BodyContents) -> Self {
+            FromRawPartsBodyAnalyzer {
+                rcx,
+                locals,
+                body,
+                status: Default::default(),
+            }
+        }
+
+        pub fn analyze_body(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> Option<FromRawPartsStatus> {
+            if !rcx.analyzes_body(decl) {
+                return None;
+            }
+            let body_id = *decl.body.as_ref().ok()?;
+            let body = rcx.crate_data.bodies.get(body_id)?.as_unstructured()?;
+            let body_analyzer = FromRawPartsBodyAnalyzer::new(rcx, &body.locals, &body.body);
+            Some(body_analyzer.analyze())
+        }
+
+        fn analyze(mut self) -> FromRawPartsStatus {
+            let local_defs = LocalDefs::new(self.body);
+            for block in self.body.iter() {
+                for st in &block.statements {
+                    let call = match &st.content {
+                        RawStatement::Call(call)
+                            if self.calls(call, &paths::VEC_FROM_RAW_PARTS_LIST) =>
+                        {
+                            call
+                        }
+                        _ => continue,
+                    };
+                    let flag = self.check_from_raw_parts(call, &local_defs);
+                    if !flag.is_empty() {
+                        log::trace!("Found mismatched Vec::from_raw_parts: {:?}", flag);
+                        self.status.behavior_flag |= flag;
+                        self.status.from_raw_parts.push(st.span);
+                    }
+                }
+            }
+            self.status
+        }
+
+        fn check_from_raw_parts(&self, call: &'a Call, local_defs: &LocalDefs<'a>) -> BehaviorFlag {
+            let (ptr, capacity) = match &call.args[..] {
+                [ptr, _, capacity] => (ptr, capacity),
+                _ => return BehaviorFlag::empty(),
+            };
+            let elem_ty = match &call.func {
+                FnOperand::Regular(FnPtr { generics, .. }) => generics.types.get(0.into()),
+                _ => None,
+            };
+
+            let mut flag = BehaviorFlag::empty();
+            let allocation = match pointer_origin(local_defs, ptr) {
+                PointerOrigin::Local => return BehaviorFlag::NOT_HEAP_ALLOCATED,
+                PointerOrigin::Call(origin) => match self.allocation(origin, local_defs) {
+                    Some(allocation) => allocation,
+                    None => return BehaviorFlag::NOT_HEAP_ALLOCATED,
+                },
+                PointerOrigin::Unknown => Allocation {
+                    elem_ty: None,
+                    owner: None,
+                },
+            };
+
+            if let (Some(elem_ty), Some(allocated_ty)) = (elem_ty, allocation.elem_ty) {
+                if elem_ty != allocated_ty {
+                    flag |= BehaviorFlag::ELEMENT_TYPE_MISMATCH;
+                }
+            }
+
+            match local_defs.value_def(capacity) {
+                Some(LocalDef::Call(len_call)) if self.calls(len_call, &paths::VEC_LEN_LIST) => {
+                    flag |= BehaviorFlag::CAPACITY_MISMATCH;
+                }
+                _ => {
+                    let allocated_capacity = allocation
+                        .owner
+                        .and_then(|owner| self.with_capacity_arg(owner, local_defs));
+                    if let (Some(allocated), Some(capacity)) =
+                        (allocated_capacity, const_usize(capacity))
+                    {
+                        if allocated != capacity {
+                            flag |= BehaviorFlag::CAPACITY_MISMATCH;
+                        }
+                    }
+                }
+            }
+            flag
+        }
+
+        /// Returns the allocation a pointer returned by `origin` points to, or
+        /// `None` if it may not be heap allocated. Pointers returned by unknown
+        /// functions point to an unknown allocation.
+        fn allocation(&self, origin: &'a Call, local_defs: &LocalDefs) -> Option<Allocation<'a>> {
+            let owner = origin
+                .args
+                .first()
+                .and_then(|arg| local_defs.operand_root(arg));
+            let elem_ty = match &origin.func {
+                FnOperand::Regular(FnPtr { generics, .. }) => {
+                    generics.types.get(0.into()).map(slice_elem_ty)
+                }
+                _ => None,
+            };
+
+            if self.calls(origin, &paths::HEAP_PTR_LIST) {
+                Some(Allocation { elem_ty, owner })
+            } else if self.calls(origin, &paths::BUFFER_PTR_LIST) {
+                // The slice may be borrowed from a `Vec`
+                let vec = owner.and_then(|owner| {
+                    local_defs
+                        .origins(owner)
+                        .into_iter()
+                        .find(|origin| self.is_vec(*origin))
+                });
+                vec.map(|vec| Allocation {
+                    elem_ty,
+                    owner: Some(vec),
+                })
+            } else {
+                Some(Allocation {
+                    elem_ty: None,
+                    owner: None,
+                })
+            }
+        }
+
+        /// Returns the constant capacity of `Vec::with_capacity(c)` if it defines `owner`.
+        fn with_capacity_arg(&self, owner: VarId, local_defs: &LocalDefs) -> Option<u64> {
+            match local_defs.unique_def(owner)? {
+                LocalDef::Call(call) if self.calls(call, &paths::VEC_WITH_CAPACITY_LIST) => {
+                    const_usize(call.args.first()?)
+                }
+                _ => None,
+            }
+        }
+
+        fn is_vec(&self, var_id: VarId) -> bool {
+            match self.locals.vars.get(var_id).map(|var| var.ty.kind()) {
+                Some(TyKind::Adt(TypeId::Adt(id), _)) => self
+                    .rcx
+                    .crate_data
+                    .type_decls
+                    .get(*id)
+                    .map_or(false, |decl| {
+                        paths::VEC_LIST
+                            .contains(self.rcx, &decl.item_meta.name)
+                            .is_some()
+                    }),
+                _ => false,
+            }
+        }
+
+        fn calls(&self, call: &Call, path_set: &PathSet) -> bool {
+            match &call.func {
+                FnOperand::Regular(FnPtr {
+                    func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                    ..
+                }) => self
+                    .rcx
+                    .crate_data
+                    .fun_decls
+                    .get(*callee_did)
+                    .map_or(false, |decl| {
+                        path_set.contains(self.rcx, &decl.item_meta.name).is_some()
+                    }),
+                _ => false,
+            }
+        }
+    }
+
+    /// Follows the copies and casts of a pointer back to its definition.
+    fn pointer_origin<'a>(local_defs: &LocalDefs<'a>, ptr: &'a Operand) -> PointerOrigin<'a> {
+        let mut place = match local_defs::operand_place(ptr) {
+            Some(place) => place,
+            None => return PointerOrigin::Unknown,
+        };
+        let mut visited = HashSet::new();
+        while visited.insert(place.var_id) && place.projection.is_empty() {
+            match local_defs.unique_def(place.var_id) {
+                Some(LocalDef::Assign(
+                    Rvalue::Use(Operand::Copy(next) | Operand::Move(next))
+                    | Rvalue::UnaryOp(UnOp::Cast(_), Operand::Copy(next) | Operand::Move(next)),
+                )) => place = next,
+                Some(LocalDef::Assign(Rvalue::Ref(borrowed, _) | Rvalue::RawPtr(borrowed, _)))
+                    if borrowed.projection.is_empty() =>
+                {
+                    return PointerOrigin::Local
+                }
+                Some(LocalDef::Call(call)) => return PointerOrigin::Call(call),
+                _ => break,
+            }
+        }
+        PointerOrigin::Unknown
+    }
+
+    /// The element type of a slice, or the type itself.
+    fn slice_elem_ty(ty: &Ty) -> &Ty {
+        match ty.kind() {
+            TyKind::Adt(TypeId::Builtin(BuiltinTy::Slice), args) => {
+                args.types.get(0.into()).unwrap_or(ty)
+            }
+            _ => ty,
+        }
+    }
+
+    fn const_usize(op: &Operand) -> Option<u64> {
+        match op {
+            Operand::Const(c) => match &c.value {
+                RawConstantExpr::Literal(Literal::Scalar(ScalarValue::Usize(n))) => Some(*n),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BehaviorFlag: u8 {
+        // The pointer may not come from a heap allocation (slice `as_ptr`, borrowed local)
+        const NOT_HEAP_ALLOCATED = 0b00000001;
+        // The allocation has another element type
+        const ELEMENT_TYPE_MISMATCH = 0b00000010;
+        // The capacity differs from the allocated one (`len()`, another constant)
+        const CAPACITY_MISMATCH = 0b00000100;
+    }
+}
+
+impl IntoReportLevel for BehaviorFlag {
+    fn report_level(&self) -> ReportLevel {
+        let high = BehaviorFlag::NOT_HEAP_ALLOCATED;
+        let med = BehaviorFlag::ELEMENT_TYPE_MISMATCH | BehaviorFlag::CAPACITY_MISMATCH;
+
+        if !(*self & high).is_empty() {
+            ReportLevel::Error
+        } else if !(*self & med).is_empty() {
+            ReportLevel::Warning
+        } else {
+            ReportLevel::Info
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! Mutable aliasing detector for references created from raw pointers:
+//! - two `&mut` created from the same raw pointer and live at the same time,
+//! - `&mut` returned from a function only taking shared references, derived
+//!   from them through a raw pointer (`fn get_mut(&self) -> &mut T`).
+
+use std::collections::HashSet;
+
+use bitflags::bitflags;
+use if_chain::if_chain;
+use termcolor::Color;
+
+use crate::rudra::analysis::{AnalysisKind, IntoReportLevel};
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::graph::reachable_from;
+use crate::rudra::local_defs::{self, LocalDef, LocalDefs};
+use crate::rudra::paths;
+use crate::rudra::report::{rudra_report, Report, ReportLevel};
+use crate::rudra::utils::{self, LabelKind};
+
+use charon_lib::ast::meta::Span;
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::types::{RefKind, TyKind};
+use charon_lib::ullbc_ast::{
+    BlockId, BodyContents, BorrowKind, Call, FnOperand, FnPtr, FunDecl, FunId,
+    FunIdOrTraitMethodRef, Locals, Place, RawStatement, Rvalue, VarId,
+};
+
+#[derive(Clone, Copy)]
+pub struct MutAliasingChecker<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+}
+
+impl<'tcx> MutAliasingChecker<'tcx> {
+    pub fn new(rcx: RudraCtxt<'tcx>) -> Self {
+        MutAliasingChecker { rcx }
+    }
+
+    pub fn analyze(self) {
+        // Iterate over all functions
+        for decl in self.rcx.crate_data.fun_decls.iter() {
+            if let Some(status) = inner::MutAliasingBodyAnalyzer::analyze_body(self.rcx, decl) {
+                let behavior_flag = status.behavior_flag();
+                let exposure = self.rcx.api_exposure(decl.def_id);
+                let report_level = exposure.adjust(behavior_flag.report_level());
+                if !behavior_flag.is_empty() && report_level >= self.rcx.report_level() {
+                    let mut color_span = if let Some(span) =
+                        utils::ColorSpan::new(&self.rcx.crate_data, decl.item_meta.span)
+                    {
+                        span
+                    } else {
+                        continue;
+                    };
+
+                    for &span in status.mut_ref_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Red,
+                            span,
+                            "mutable reference from a raw pointer",
+                        );
+                    }
+
+                    rudra_report(
+                        Report::with_color_span(
+                            report_level,
+                            AnalysisKind::MutAliasing(behavior_flag),
+                            format!(
+                                "Potential mutable aliasing through a raw pointer in `{}`",
+                                self.rcx.crate_data.into_fmt().format_object(decl.def_id)
+                            ),
+                            &color_span,
+                        )
+                        .with_exposure(exposure),
+                    )
+                }
+            }
+        }
+    }
+}
+
+mod inner {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct MutAliasingStatus {
+        mut_refs: Vec<Span>,
+        behavior_flag: BehaviorFlag,
+    }
+
+    impl MutAliasingStatus {
+        pub fn behavior_flag(&self) -> BehaviorFlag {
+            self.behavior_flag
+        }
+
+        pub fn mut_ref_spans(&self) -> &Vec<Span> {
+            &self.mut_refs
+        }
+
+        fn add_mut_ref(&mut self, span: Span) {
+            if !self.mut_refs.contains(&span) {
+                self.mut_refs.push(span);
+            }
+        }
+    }
+
+    /// A `&mut` created from a raw pointer
+    struct MutRef {
+        block_id: BlockId,
+        statement_idx: usize,
+        span: Span,
+        /// The local holding the reference (or the `Option` returned by `as_mut`)
+        dest: VarId,
+        /// The place the raw pointer has been derived from
+        pointer: Place,
+    }
+
+    pub struct MutAliasingBodyAnalyzer<'a, 'tcx> {
+        rcx: RudraCtxt<'tcx>,
+        decl: &'a FunDecl,
+        locals: &'a Locals,
+        body: &'a BodyContents,
+        status: MutAliasingStatus,
+    }
+
+    impl<'a, 'tcx> MutAliasingBodyAnalyzer<'a, 'tcx> {
+        fn new(
+            rcx: RudraCtxt<'tcx>,
+            decl: &'a FunDecl,
+            locals: &'a Locals,
+            body: &'a BodyContents,
+        ) -> Self {
+            MutAliasingBodyAnalyzer {
+                rcx,
+                decl,
+                locals,
+                body,
+                status: Default::default(),
+            }
+        }
+
+        pub fn analyze_body(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> Option<MutAliasingStatus> {
+            if !rcx.analyzes_body(decl) {
+                return None;
+            }
+            let body_id = *decl.body.as_ref().ok()?;
+            let body = rcx.crate_data.bodies.get(body_id)?.as_unstructured()?;
+            let body_analyzer = MutAliasingBodyAnalyzer::new(rcx, decl, &body.locals, &body.body);
+            Some(body_analyzer.analyze())
+        }
+
+        fn analyze(mut self) -> MutAliasingStatus {
+            let local_defs = LocalDefs::new(self.body);
+            let mut_refs = self.mut_refs(&local_defs);
+
+            for (i, first) in mut_refs.iter().enumerate() {
+                let first_uses = local_defs.derived(first.dest);
+                for second in mut_refs[i + 1..]
+                    .iter()
+                    .filter(|r| r.pointer == first.pointer)
+                {
+                    // Either reference may be created first
+                    if self.used_after(first, second, &first_uses)
+                        || self.used_after(second, first, &local_defs.derived(second.dest))
+                    {
+                        log::trace!("Found aliasing &mut from {:?}", first.pointer);
+                        self.status.behavior_flag |= BehaviorFlag::ALIASING_MUT;
+                        self.status.add_mut_ref(first.span);
+                        self.status.add_mut_ref(second.span);
+                    }
+                }
+            }
+
+            if self.launders_shared_ref() {
+                // The returned reference, stored in the return local
+                let return_var = VarId::from_usize(0);
+                for mut_ref in mut_refs.iter() {
+                    if !local_defs.derived(mut_ref.dest).contains(&return_var) {
+                        continue;
+                    }
+                    if local_defs
+                        .origins(mut_ref.pointer.var_id)
+                        .iter()
+                        .any(|origin| self.is_shared_ref_arg(*origin))
+                    {
+                        log::trace!("Found &mut derived from a shared reference");
+                        self.status.behavior_flag |= BehaviorFlag::MUT_FROM_SHARED;
+                        self.status.add_mut_ref(mut_ref.span);
+                    }
+                }
+            }
+
+            self.status
+        }
+
+        /// Collects the `&mut` created from raw pointers, either with `as_mut`
+        /// or by reborrowing a dereferenced raw pointer (`&mut *p`).
+        fn mut_refs(&self, local_defs: &LocalDefs) -> Vec<MutRef> {
+            let mut mut_refs = Vec::new();
+            for (block_id, block) in self.body.iter_indexed() {
+                for (statement_idx, st) in block.statements.iter().enumerate() {
+                    let (dest, pointer) = match &st.content {
+                        RawStatement::Assign(
+                            dest,
+                            Rvalue::Ref(place, BorrowKind::Mut | BorrowKind::TwoPhaseMut),
+                        ) => match self.rcx.raw_ptr_deref(self.locals, place) {
+                            // The pointer is `place` up to its dereference
+                            Some(idx) => {
+                                let mut pointer = place.clone();
+                                pointer.projection.truncate(idx);
+                                (dest, pointer)
+                            }
+                            None => continue,
+                        },
+                        RawStatement::Call(call) if self.is_as_mut(call) => {
+                            match call.args.first().and_then(local_defs::operand_place) {
+                                Some(pointer) => (&call.dest, pointer.clone()),
+                                None => continue,
+                            }
+                        }
+                        _ => continue,
+                    };
+                    if !dest.projection.is_empty() {
+                        continue;
+                    }
+                    mut_refs.push(MutRef {
+                        block_id,
+                        statement_idx,
+                        span: st.span,
+                        dest: dest.var_id,
+                        pointer: pointer_origin(local_defs, &pointer),
+                    });
+                }
+            }
+            mut_refs
+        }
+
+        fn is_as_mut(&self, call: &Call) -> bool {
+            if_chain! {
+                if let FnOperand::Regular(FnPtr {
+                    func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                    ..
+                }) = &call.func;
+                if let Some(decl) = self.rcx.crate_data.fun_decls.get(*callee_did);
+                then {
+                    paths::PTR_AS_MUT_LIST.contains(self.rcx, &decl.item_meta.name).is_some()
+                } else {
+                    false
+                }
+            }
+        }
+
+        /// Returns true if a use of `first` (or of a value derived from it)
+        /// can be executed after `second`, which can itself be executed after `first`.
+        fn used_after(&self, first: &MutRef, second: &MutRef, first_uses: &HashSet<VarId>) -> bool {
+            let uses = |st: &RawStatement| {
+                local_defs::statement_places(st)
+                    .iter()
+                    .any(|place| first_uses.contains(&place.var_id))
+            };
+
+            let after_first = reachable_from(self.body, first.block_id.index());
+            let second_after_first = after_first[second.block_id.index()]
+                || (second.block_id == first.block_id
+                    && second.statement_idx > first.statement_idx);
+            if !second_after_first {
+                return false;
+            }
+
+            let statements = &self.body[second.block_id].statements;
+            if statements[second.statement_idx + 1..]
+                .iter()
+                .any(|st| uses(&st.content))
+            {
+                return true;
+            }
+            let after_second = reachable_from(self.body, second.block_id.index());
+            self.body.iter_indexed().any(|(id, block)| {
+                after_second[id.index()] && block.statements.iter().any(|st| uses(&st.content))
+            })
+        }
+
+        /// Returns true if the function is safe, returns a `&mut` and only
+        /// takes shared references.
+        fn launders_shared_ref(&self) -> bool {
+            let signature = &self.decl.signature;
+            !signature.is_unsafe
+                && matches!(signature.output.kind(), TyKind::Ref(_, _, RefKind::Mut))
+                && !signature
+                    .inputs
+                    .iter()
+                    .any(|ty| matches!(ty.kind(), TyKind::Ref(_, _, RefKind::Mut)))
+                && signature
+                    .inputs
+                    .iter()
+                    .any(|ty| matches!(ty.kind(), TyKind::Ref(_, _, RefKind::Shared)))
+        }
+
+        fn is_shared_ref_arg(&self, var_id: VarId) -> bool {
+            // The locals of the arguments come right after the return local
+            let is_arg = var_id.index() >= 1 && var_id.index() <= self.locals.arg_count;
+            is_arg
+                && matches!(
+                    self.locals.vars.get(var_id).map(|var| var.ty.kind()),
+                    Some(TyKind::Ref(_, _, RefKind::Shared))
+                )
+        }
+    }
+
+    /// Returns the place a pointer has been derived from: the pointers stored
+    /// in different fields of the same local are different pointers.
+    fn pointer_origin(local_defs: &LocalDefs, pointer: &Place) -> Place {
+        if !pointer.projection.is_empty() {
+            return pointer.clone();
+        }
+        match local_defs.unique_def(pointer.var_id) {
+            // `NonNull::as_mut(&mut (*self).ptr)`
+            Some(LocalDef::Assign(Rvalue::Ref(next, _) | Rvalue::RawPtr(next, _)))
+                if !next.projection.is_empty() =>
+            {
+                next.clone()
+            }
+            _ => Place {
+                var_id: local_defs.place_root(pointer),
+                projection: Vec::new(),
+            },
+        }
+    }
+}
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BehaviorFlag: u8 {
+        // Two `&mut` created from the same raw pointer are live at the same time
+        const ALIASING_MUT = 0b00000001;
+        // A returned `&mut` is derived from a shared reference through a raw pointer
+        const MUT_FROM_SHARED = 0b00000010;
+    }
+}
+
+impl IntoReportLevel for BehaviorFlag {
+    fn report_level(&self) -> ReportLevel {
+        let high = BehaviorFlag::MUT_FROM_SHARED;
+        let med = BehaviorFlag::ALIASING_MUT;
+
+        if !(*self & high).is_empty() {
+            ReportLevel::Error
+        } else if !(*self & med).is_empty() {
+            ReportLevel::Warning
+        } else {
+            ReportLevel::Info
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Raw pointer cast checker: `*const A as *const B` where `B` has a larger
+//! alignment or size than `A`, and the result is then dereferenced or passed
+//! to `ptr::read`/`as_ref`. The layouts are approximated for the types
+//! declared in the crate and the primitive types (64-bit target).
+
+use std::collections::HashSet;
+
+use bitflags::bitflags;
+use termcolor::Color;
+
+use crate::rudra::analysis::{AnalysisKind, IntoReportLevel};
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::local_defs::{self, LocalDefs};
+use crate::rudra::paths;
+use crate::rudra::report::{rudra_report, Report, ReportLevel};
+use crate::rudra::utils::{self, LabelKind};
+
+use charon_lib::ast::meta::Span;
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::types::{
+    BuiltinTy, ConstGeneric, FloatTy, IntegerTy, LiteralTy, Ty, TyKind, TypeDeclId, TypeDeclKind,
+    TypeId,
+};
+use charon_lib::ullbc_ast::{
+    BodyContents, CastKind, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, Literal,
+    ProjectionElem, RawStatement, Rvalue, ScalarValue, UnOp, VarId,
+};
+
+#[derive(Clone, Copy)]
+pub struct PtrCastChecker<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+}
+
+impl<'tcx> PtrCastChecker<'tcx> {
+    pub fn new(rcx: RudraCtxt<'tcx>) -> Self {
+        PtrCastChecker { rcx }
+    }
+
+    pub fn analyze(self) {
+        // Iterate over all functions
+        for decl in self.rcx.crate_data.fun_decls.iter() {
+            if let Some(status) = inner::PtrCastBodyAnalyzer::analyze_body(self.rcx, decl) {
+                let behavior_flag = status.behavior_flag();
+                let exposure = self.rcx.api_exposure(decl.def_id);
+                let report_level = exposure.adjust(behavior_flag.report_level());
+                if !behavior_flag.is_empty() && report_level >= self.rcx.report_level() {
+                    let mut color_span = if let Some(span) =
+                        utils::ColorSpan::new(&self.rcx.crate_data, decl.item_meta.span)
+                    {
+                        span
+                    } else {
+                        continue;
+                    };
+
+                    for &span in status.cast_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Red,
+                            span,
+                            "pointer cast to a larger type",
+                        );
+                    }
+
+                    for &span in status.access_spans() {
+                        color_span.add_label(
+                            LabelKind::Secondary,
+                            Color::Yellow,
+                            span,
+                            "access through the cast pointer",
+                        );
+                    }
+
+                    rudra_report(
+                        Report::with_color_span(
+                            report_level,
+                            AnalysisKind::PtrCast(behavior_flag),
+                            format!(
+                                "Pointer cast to a larger type in `{}`",
+                                self.rcx.crate_data.into_fmt().format_object(decl.def_id)
+                            ),
+                            &color_span,
+                        )
+                        .with_exposure(exposure),
+                    )
+                }
+            }
+        }
+    }
+}
+
+mod inner {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct PtrCastStatus {
+        casts: Vec<Span>,
+        accesses: Vec<Span>,
+        behavior_flag: BehaviorFlag,
+    }
+
+    impl PtrCastStatus {
+        pub fn behavior_flag(&self) -> BehaviorFlag {
+            self.behavior_flag
+        }
+
+        pub fn cast_spans(&self) -> &Vec<Span> {
+            &self.casts
+        }
+
+        pub fn access_spans(&self) -> &Vec<Span> {
+            &self.accesses
+        }
+    }
+
+    /// Approximate size and alignment of a type, in bytes
+    #[derive(Clone, Copy)]
+    struct Layout {
+        size: u64,
+        align: u64,
+    }
+
+    pub struct PtrCastBodyAnalyzer<'a, 'tcx> {
+        rcx: RudraCtxt<'tcx>,
+        body: &'a BodyContents,
+        status: PtrCastStatus,
+    }
+
+    impl<'a, 'tcx> PtrCastBodyAnalyzer<'a, 'tcx> {
+        fn new(rcx: RudraCtxt<'tcx>, body: &'a BodyContents) -> Self {
+            PtrCastBodyAnalyzer {
+                rcx,
+                body,
+                status: Default::default(),
+            }
+        }
+
+        pub fn analyze_body(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> Option<PtrCastStatus> {
+            if !rcx.analyzes_body(decl) {
+                return None;
+            }
+            let body_id = *decl.body.as_ref().ok()?;
+            let body = rcx.crate_data.bodies.get(body_id)?.as_unstructured()?;
+            let body_analyzer = PtrCastBodyAnalyzer::new(rcx, &body.body);
+            Some(body_analyzer.analyze())
+        }
+
+        fn analyze(mut self) -> PtrCastStatus {
+            let local_defs = LocalDefs::new(self.body);
+            for block in self.body.iter() {
+                for st in &block.statements {
+                    let (dest, from, to) = match &st.content {
+                        RawStatement::Assign(
+                            dest,
+                            Rvalue::UnaryOp(UnOp::Cast(CastKind::RawPtr(from, to)), _),
+                        ) if dest.projection.is_empty() => (dest.var_id, from, to),
+                        _ => continue,
+                    };
+                    let flag = self.check_cast(from, to);
+                    if flag.is_empty() {
+                        continue;
+                    }
+
+                    let accesses = self.accesses(&local_defs.derived(dest));
+                    if !accesses.is_empty() {
+                        log::trace!("Found pointer cast to a larger type: {:?}", flag);
+                        self.status.behavior_flag |= flag;
+                        self.status.casts.push(st.span);
+                        self.status.accesses.extend(accesses);
+                    }
+                }
+            }
+            self.status
+        }
+
+        fn check_cast(&self, from: &Ty, to: &Ty) -> BehaviorFlag {
+            let (from, to) = match (from.kind(), to.kind()) {
+                (TyKind::RawPtr(from, _), TyKind::RawPtr(to, _)) => (from, to),
+                _ => return BehaviorFlag::empty(),
+            };
+            let (from, to) = match (self.layout(from), self.layout(to)) {
+                (Some(from), Some(to)) => (from, to),
+                _ => return BehaviorFlag::empty(),
+            };
+
+            let mut flag = BehaviorFlag::empty();
+            if to.align > from.align {
+                flag |= BehaviorFlag::ALIGNMENT_INCREASE;
+            }
+            if to.size > from.size {
+                flag |= BehaviorFlag::SIZE_INCREASE;
+            }
+            flag
+        }
+
+        /// Returns the spans of the statements dereferencing one of `ptrs`,
+        /// or passing it to `ptr::read`/`ptr::write`/`as_ref`/`as_mut`.
+        fn accesses(&self, ptrs: &HashSet<VarId>) -> Vec<Span> {
+            let mut accesses = Vec::new();
+            for block in self.body.iter() {
+                for st in &block.statements {
+                    let dereferenced =
+                        local_defs::statement_places(&st.content)
+                            .iter()
+                            .any(|place| {
+                                ptrs.contains(&place.var_id)
+                                    && matches!(
+                                        place.projection.first(),
+                                        Some(ProjectionElem::Deref)
+                                    )
+                            });
+                    if dereferenced || self.passed_to_access_fn(&st.content, ptrs) {
+                        accesses.push(st.span);
+                    }
+                }
+            }
+            accesses
+        }
+
+        fn passed_to_access_fn(&self, st: &RawStatement, ptrs: &HashSet<VarId>) -> bool {
+            let (callee_did, args) = match st {
+                RawStatement::Call(call) => match &call.func {
+                    FnOperand::Regular(FnPtr {
+                        func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                        ..
+                    }) => (callee_did, &call.args),
+                    _ => return false,
+                },
+                _ => return false,
+            };
+            let accesses_ptr = args
+                .first()
+                .and_then(local_defs::operand_place)
+                .map_or(false, |place| ptrs.contains(&place.var_id));
+            accesses_ptr
+                && self
+                    .rcx
+                    .crate_data
+                    .fun_decls
+                    .get(*callee_did)
+                    .map_or(false, |decl| {
+                        paths::PTR_ACCESS_LIST
+                            .contains(self.rcx, &decl.item_meta.name)
+                            .is_some()
+                    })
+        }
+
+        fn layout(&self, ty: &Ty) -> Option<Layout> {
+            self.layout_inner(ty, &mut HashSet::new())
+        }
+
+        fn layout_inner(&self, ty: &Ty, visited: &mut HashSet<TypeDeclId>) -> Option<Layout> {
+            match ty.kind() {
+                TyKind::Literal(lit) => {
+                    let size = literal_size(lit);
+                    Some(Layout { size, align: size })
+                }
+                TyKind::Ref(_, pointee, _) | TyKind::RawPtr(pointee, _) => {
+                    // Pointers to unsized types are fat
+                    let size = match pointee.kind() {
+                        TyKind::Adt(TypeId::Builtin(BuiltinTy::Slice | BuiltinTy::Str), _)
+                        | TyKind::DynTrait(_) => 16,
+                        _ => 8,
+                    };
+                    Some(Layout { size, align: 8 })
+                }
+                TyKind::Adt(TypeId::Builtin(BuiltinTy::Array), args) => {
+                    let elem = self.layout_inner(args.types.get(0.into())?, visited)?;
+                    let len = match args.const_generics.get(0.into())? {
+                        ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(len))) => *len,
+                        _ => return None,
+                    };
+                    Some(Layout {
+                        size: elem.size * len,
+                        align: elem.align,
+                    })
+                }
+                TyKind::Adt(TypeId::Tuple, args) => {
+                    let fields = args
+                        .types
+                        .iter()
+                        .map(|ty| self.layout_inner(ty, visited))
+                        .collect::<Option<Vec<_>>>()?;
+                    Some(aggregate_layout(&fields))
+                }
+                TyKind::Adt(TypeId::Adt(id), _) => {
+                    if !visited.insert(*id) {
+                        return None;
+                    }
+                    let layout = match &self.rcx.crate_data.type_decls.get(*id)?.kind {
+                        // The field types are not substituted: generic fields have no layout
+                        TypeDeclKind::Struct(fields) => fields
+                            .iter()
+                            .map(|field| self.layout_inner(&field.ty, visited))
+                            .collect::<Option<Vec<_>>>()
+                            .map(|fields| aggregate_layout(&fields)),
+                        _ => None,
+                    };
+                    visited.remove(id);
+                    layout
+                }
+                _ => None,
+            }
+        }
+    }
+
+    fn literal_size(lit: &LiteralTy) -> u64 {
+        match lit {
+            LiteralTy::Integer(int_ty) => match int_ty {
+                IntegerTy::I8 | IntegerTy::U8 => 1,
+                IntegerTy::I16 | IntegerTy::U16 => 2,
+                IntegerTy::I32 | IntegerTy::U32 => 4,
+                IntegerTy::I64 | IntegerTy::U64 | IntegerTy::Isize | IntegerTy::Usize => 8,
+                IntegerTy::I128 | IntegerTy::U128 => 16,
+            },
+            // `f16` and `f128` are unstable
+            LiteralTy::Float(FloatTy::F32) => 4,
+            LiteralTy::Float(_) => 8,
+            LiteralTy::Bool => 1,
+            LiteralTy::Char => 4,
+        }
+    }
+
+    /// The fields of a `repr(Rust)` aggregate may be reordered: we assume there
+    /// is no padding between them.
+    fn aggregate_layout(fields: &[Layout]) -> Layout {
+        let align = fields.iter().map(|field| field.align).max().unwrap_or(1);
+        let size = fields.iter().map(|field| field.size).sum::<u64>();
+        Layout {
+            size: (size + align - 1) / align * align,
+            align,
+        }
+    }
+}
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BehaviorFlag: u8 {
+        // The target type has a larger alignment than the source type
+        const ALIGNMENT_INCREASE = 0b00000001;
+        // The target type is larger than the source type
+        const SIZE_INCREASE = 0b00000010;
+    }
+}
+
+impl IntoReportLevel for BehaviorFlag {
+    fn report_level(&self) -> ReportLevel {
+        let high = BehaviorFlag::ALIGNMENT_INCREASE;
+        let med = BehaviorFlag::SIZE_INCREASE;
+
+        if !(*self & high).is_empty() {
+            ReportLevel::Error
+        } else if !(*self & med).is_empty() {
+            ReportLevel::Warning
+        } else {
+            ReportLevel::Info
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Transmute sanity checker, based on the source and target types of each
+//! `transmute::<Src, Dst>` call:
+//! - `&T` to `&mut T`,
+//! - lifetime extension of a reference to `'static`,
+//! - integer to reference or `Box`,
+//! - to a type with invalid bit patterns (`bool`, `char`, enums...) from a type without.
+
+use bitflags::bitflags;
+use termcolor::Color;
+
+use crate::rudra::analysis::{AnalysisKind, IntoReportLevel};
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::local_defs::LocalDefs;
+use crate::rudra::paths;
+use crate::rudra::report::{rudra_report, Report, ReportLevel};
+use crate::rudra::utils::{self, LabelKind};
+
+use charon_lib::ast::meta::Span;
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::types::{BuiltinTy, GenericArgs, LiteralTy, RefKind, Region, Ty, TyKind, TypeId};
+use charon_lib::ullbc_ast::{
+    BodyContents, Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, FunSig, Operand,
+    RawStatement,
+};
+
+#[derive(Clone, Copy)]
+pub struct TransmuteChecker<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+}
+
+impl<'tcx> TransmuteChecker<'tcx> {
+    pub fn new(rcx: RudraCtxt<'tcx>) -> Self {
+        TransmuteChecker { rcx }
+    }
+
+    pub fn analyze(self) {
+        // Iterate over all functions
+        for decl in self.rcx.crate_data.fun_decls.iter() {
+            if let Some(status) = inner::TransmuteBodyAnalyzer::analyze_body(self.rcx, decl) {
+                let behavior_flag = status.behavior_flag();
+                let exposure = self.rcx.api_exposure(decl.def_id);
+                let report_level = exposure.adjust(behavior_flag.report_level());
+                if !behavior_flag.is_empty() && report_level >= self.rcx.report_level() {
+                    let mut color_span = if let Some(span) =
+                        utils::ColorSpan::new(&self.rcx.crate_data, decl.item_meta.span)
+                    {
+                        span
+                    } else {
+                        continue;
+                    };
+
+                    for &span in status.transmute_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Red,
+                            span,
+                            "suspicious transmute",
+                        );
+                    }
+
+                    rudra_report(
+                        Report::with_color_span(
+                            report_level,
+                            AnalysisKind::Transmute(behavior_flag),
+                            format!(
+                                "Suspicious transmute in `{}`",
+                                self.rcx.crate_data.into_fmt().format_object(decl.def_id)
+                            ),
+                            &color_span,
+                        )
+                        .with_exposure(exposure),
+                    )
+                }
+            }
+        }
+    }
+}
+
+mod inner {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct TransmuteStatus {
+        transmutes: Vec<Span>,
+        behavior_flag: BehaviorFlag,
+    }
+
+    impl TransmuteStatus {
+        pub fn behavior_flag(&self) -> BehaviorFlag {
+            self.behavior_flag
+        }
+
+        pub fn transmute_spans(&self) -> &Vec<Span> {
+            &self.transmutes
+        }
+    }
+
+    pub struct TransmuteBodyAnalyzer<'a, 'tcx> {
+        rcx: RudraCtxt<'tcx>,
+        /// The signature of the analyzed function, which keeps the regions
+        /// erased in the body
+        signature: &'a FunSig,
+        body: &'a BodyContents,
+        status: TransmuteStatus,
+    }
+
+    impl<'a, 'tcx> TransmuteBodyAnalyzer<'a, 'tcx> {
+        fn new(rcx: RudraCtxt<'tcx>, signature: &'a FunSig, body: &'a BodyContents) -> Self {
+            TransmuteBodyAnalyzer {
+                rcx,
+                signature,
+                body,
+                status: Default::default(),
+            }
+        }
+
+        pub fn analyze_body(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> Option<TransmuteStatus> {
+            if !rcx.analyzes_body(decl) {
+                return None;
+            }
+            let body_id = *decl.body.as_ref().ok()?;
+            let body = rcx.crate_data.bodies.get(body_id)?.as_unstructured()?;
+            let body_analyzer = TransmuteBodyAnalyzer::new(rcx, &decl.signature, &body.body);
+            Some(body_analyzer.analyze())
+        }
+
+        fn analyze(mut self) -> TransmuteStatus {
+            let local_defs = LocalDefs::new(self.body);
+            for block in self.body.iter() {
+                for st in &block.statements {
+                    let (callee_did, generics, args) = match &st.content {
+                        RawStatement::Call(Call {
+                            func:
+                                FnOperand::Regular(FnPtr {
+                                    func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                                    generics,
+                                }),
+                            args,
+                            ..
+                        }) => (callee_did, generics, args),
+                        _ => continue,
+                    };
+                    let name = match self.rcx.crate_data.fun_decls.get(*callee_did) {
+                        Some(decl) => &decl.item_meta.name,
+                        None => continue,
+                    };
+                    if paths::TRANSMUTE_LIST.contains(self.rcx, name).is_none() {
+                        continue;
+                    }
+
+                    let flag = self.check_transmute(generics, args, &local_defs);
+                    if !flag.is_empty() {
+                        log::trace!("Found suspicious transmute: {:?}", flag);
+                        self.status.behavior_flag |= flag;
+                        self.status.transmutes.push(st.span);
+                    }
+                }
+            }
+            self.status
+        }
+
+        fn check_transmute(
+            &self,
+            generics: &GenericArgs,
+            args: &[Operand],
+            local_defs: &LocalDefs,
+        ) -> BehaviorFlag {
+            let (src, dst) = match (generics.types.get(0.into()), generics.types.get(1.into())) {
+                (Some(src), Some(dst)) => (src, dst),
+                _ => return BehaviorFlag::empty(),
+            };
+
+            let mut flag = BehaviorFlag::empty();
+            match (src.kind(), dst.kind()) {
+                (TyKind::Ref(src_region, _, src_kind), TyKind::Ref(dst_region, _, dst_kind)) => {
+                    if matches!((src_kind, dst_kind), (RefKind::Shared, RefKind::Mut)) {
+                        flag |= BehaviorFlag::REF_TO_MUT;
+                    }
+                    if self.extends_lifetime(src_region, dst_region, args, local_defs) {
+                        flag |= BehaviorFlag::LIFETIME_EXTENSION;
+                    }
+                }
+                (TyKind::Literal(LiteralTy::Integer(_)), _) if must_be_valid_pointer(dst) => {
+                    flag |= BehaviorFlag::INT_TO_REF;
+                }
+                _ => (),
+            }
+            if !flag.contains(BehaviorFlag::INT_TO_REF)
+                && self.rcx.has_niche(dst)
+                && !self.rcx.has_niche(src)
+            {
+                flag |= BehaviorFlag::INVALID_BIT_PATTERN;
+            }
+            flag
+        }
+
+        /// A reference is transmuted to a different lifetime, other than
+        /// `'static` to a shorter one. The outlives relations are not known,
+        /// so any other pair of distinct lifetimes is suspicious.
+        ///
+        /// When the regions of the transmute are erased, they are taken from
+        /// the signature: the transmuted reference comes from an argument and
+        /// the result is returned with the lifetime of the output.
+        fn extends_lifetime(
+            &self,
+            src_region: &Region,
+            dst_region: &Region,
+            args: &[Operand],
+            local_defs: &LocalDefs,
+        ) -> bool {
+            if !matches!(src_region, Region::Erased) && !matches!(dst_region, Region::Erased) {
+                return lifetime_differs(src_region, dst_region);
+            }
+            let output_region = match self.signature.output.kind() {
+                TyKind::Ref(region, ..) => region,
+                _ => return false,
+            };
+            // The locals of the arguments come right after the return local
+            let arg_ty = args
+                .first()
+                .and_then(|arg| local_defs.operand_root(arg))
+                .and_then(|var_id| var_id.index().checked_sub(1))
+                .and_then(|idx| self.signature.inputs.get(idx));
+            match arg_ty.map(|ty| ty.kind()) {
+                Some(TyKind::Ref(arg_region, ..)) => lifetime_differs(arg_region, output_region),
+                _ => false,
+            }
+        }
+    }
+
+    /// Two known lifetimes which differ, where the first one is not `'static`.
+    fn lifetime_differs(src: &Region, dst: &Region) -> bool {
+        !matches!(src, Region::Static | Region::Erased)
+            && !matches!(dst, Region::Erased)
+            && src != dst
+    }
+
+    /// References and `Box`es: pointers that must be non-null and point to a valid value.
+    fn must_be_valid_pointer(ty: &Ty) -> bool {
+        matches!(
+            ty.kind(),
+            TyKind::Ref(..) | TyKind::Adt(TypeId::Builtin(BuiltinTy::Box), _)
+        )
+    }
+}
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BehaviorFlag: u8 {
+        // `&T` to `&mut T`
+        const REF_TO_MUT = 0b00000001;
+        // A reference to a reference with the `'static` lifetime
+        const LIFETIME_EXTENSION = 0b00000010;
+        // An integer to a reference or a `Box`
+        const INT_TO_REF = 0b00000100;
+        // To a type with invalid bit patterns from a type without
+        const INVALID_BIT_PATTERN = 0b00001000;
+    }
+}
+
+impl IntoReportLevel for BehaviorFlag {
+    fn report_level(&self) -> ReportLevel {
+        let high = BehaviorFlag::REF_TO_MUT | BehaviorFlag::INT_TO_REF;
+        let med = BehaviorFlag::LIFETIME_EXTENSION | BehaviorFlag::INVALID_BIT_PATTERN;
+
+        if !(*self & high).is_empty() {
+            ReportLevel::Error
+        } else if !(*self & med).is_empty() {
+            ReportLevel::Warning
+        } else {
+            ReportLevel::Info
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Uninitialized memory exposure detector:
+//! - `Vec::with_capacity` followed by `set_len` before the elements are written,
+//! - `MaybeUninit::uninit` followed by `assume_init` before the value is written,
+//! - `mem::uninitialized`/`mem::zeroed` on types with drop glue or invalid bit patterns.
+
+use bitflags::bitflags;
+use termcolor::Color;
+
+use crate::rudra::analysis::{vec_set_len_to_0, AnalysisKind, IntoReportLevel};
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::graph::TaintAnalyzer;
+use crate::rudra::local_defs::LocalDefs;
+use crate::rudra::paths::{self, *};
+use crate::rudra::report::{rudra_report, Report, ReportLevel};
+use crate::rudra::utils::{self, LabelKind};
+
+use charon_lib::ast::meta::Span;
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::types::{GenericArgs, GenericParams};
+use charon_lib::ullbc_ast::{
+    BodyContents, Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, Operand,
+    RawStatement, VarId,
+};
+
+#[derive(Clone, Copy)]
+pub struct UninitExposureChecker<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+}
+
+impl<'tcx> UninitExposureChecker<'tcx> {
+    pub fn new(rcx: RudraCtxt<'tcx>) -> Self {
+        UninitExposureChecker { rcx }
+    }
+
+    pub fn analyze(self) {
+        // Iterate over all functions
+        for decl in self.rcx.crate_data.fun_decls.iter() {
+            if let Some(status) = inner::UninitExposureBodyAnalyzer::analyze_body(self.rcx, decl) {
+                let behavior_flag = status.behavior_flag();
+                let exposure = self.rcx.api_exposure(decl.def_id);
+                let report_level = exposure.adjust(behavior_flag.report_level());
+                if !behavior_flag.is_empty() && report_level >= self.rcx.report_level() {
+                    let mut color_span = if let Some(span) =
+                        utils::ColorSpan::new(&self.rcx.crate_data, decl.item_meta.span)
+                    {
+                        span
+                    } else {
+                        continue;
+                    };
+
+                    for &span in status.exposure_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Red,
+                            span,
+                            "uninitialized memory exposed",
+                        );
+                    }
+
+                    for &span in status.uninit_source_spans() {
+                        color_span.add_label(
+                            LabelKind::Secondary,
+                            Color::Yellow,
+                            span,
+                            "uninitialized memory",
+                        );
+                    }
+
+                    rudra_report(
+                        Report::with_color_span(
+                            report_level,
+                            AnalysisKind::UninitExposure(behavior_flag),
+                            format!(
+                                "Potential uninitialized memory exposure in `{}`",
+                                self.rcx.crate_data.into_fmt().format_object(decl.def_id)
+                            ),
+                            &color_span,
+                        )
+                        .with_exposure(exposure),
+                    )
+                }
+            }
+        }
+    }
+}
+
+mod inner {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct UninitExposureStatus {
+        uninit_sources: Vec<Span>,
+        exposures: Vec<Span>,
+        behavior_flag: BehaviorFlag,
+    }
+
+    impl UninitExposureStatus {
+        pub fn behavior_flag(&self) -> BehaviorFlag {
+            self.behavior_flag
+        }
+
+        pub fn uninit_source_spans(&self) -> &Vec<Span> {
+            &self.uninit_sources
+        }
+
+        pub fn exposure_spans(&self) -> &Vec<Span> {
+            &self.exposures
+        }
+    }
+
+    pub struct UninitExposureBodyAnalyzer<'a, 'tcx> {
+        rcx: RudraCtxt<'tcx>,
+        /// The generic parameters of the analyzed function
+        generics: &'a GenericParams,
+        body: &'a BodyContents,
+        status: UninitExposureStatus,
+    }
+
+    impl<'a, 'tcx> UninitExposureBodyAnalyzer<'a, 'tcx> {
+        fn new(rcx: RudraCtxt<'tcx>, generics: &'a GenericParams, body: &'a BodyContents) -> Self {
+            UninitExposureBodyAnalyzer {
+                rcx,
+                generics,
+                body,
+                status: Default::default(),
+            }
+        }
+
+        pub fn analyze_body(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> Option<UninitExposureStatus> {
+            if !rcx.analyzes_body(decl) {
+                return None;
+            }
+            let body_id = *decl.body.as_ref().ok()?;
+            let body = rcx.crate_data.bodies.get(body_id)?.as_unstructured()?;
+            let body_analyzer =
+                UninitExposureBodyAnalyzer::new(rcx, &decl.signature.generics, &body.body);
+            Some(body_analyzer.analyze())
+        }
+
+        fn analyze(mut self) -> UninitExposureStatus {
+            let local_defs = LocalDefs::new(self.body);
+            let uninit_sources = self.uninit_sources();
+            // One taint analysis per kind of uninitialized memory, as the sinks
+            // are specific to each kind
+            let mut taint_analyzers = [BehaviorFlag::VEC_SET_LEN, BehaviorFlag::ASSUME_INIT]
+                .map(|flag| (flag, TaintAnalyzer::new(self.body)));
+            // Invalid values, found without going through the CFG
+            let mut direct_flag = BehaviorFlag::empty();
+            // The exposures, with their kind, their node and their index in the node
+            let mut sinks = Vec::new();
+
+            for (id, block) in self.body.iter_indexed() {
+                // The statements are visited in order: a sink sees the uninitialized
+                // memory of the preceding statements which is not written yet, and
+                // the incoming taint if no memory was written before it.
+                let mut block_source = BehaviorFlag::empty();
+                let mut block_kill = BehaviorFlag::empty();
+                for st in &block.statements {
+                    let (callee_did, generics, args) = match &st.content {
+                        RawStatement::Call(Call {
+                            func:
+                                FnOperand::Regular(FnPtr {
+                                    func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                                    generics,
+                                }),
+                            args,
+                            ..
+                        }) => (callee_did, generics, args),
+                        _ => continue,
+                    };
+                    let name = match self.rcx.crate_data.fun_decls.get(*callee_did) {
+                        Some(decl) => &decl.item_meta.name,
+                        None => continue,
+                    };
+
+                    if let Some(pname) = paths::UNINIT_SOURCE_LIST.contains(self.rcx, name) {
+                        log::trace!("Found uninitialized memory: {pname} (block: {id})");
+                        block_source |= *UNINIT_MAP.get(pname).unwrap();
+                        self.status.uninit_sources.push(st.span);
+                    } else if let Some(pname) = paths::INIT_WRITE_LIST.contains(self.rcx, name) {
+                        let kill = self.initialized(&local_defs, &uninit_sources, pname, args);
+                        block_source.remove(kill);
+                        block_kill |= kill;
+                    } else if let Some(pname) = paths::UNINIT_SINK_LIST.contains(self.rcx, name) {
+                        if vec_set_len_to_0(args) {
+                            // `vec.set_len(0)` doesn't expose anything
+                            continue;
+                        }
+                        log::trace!("Found uninitialized memory exposure: {pname} (block: {id})");
+                        let flag = *UNINIT_MAP.get(pname).unwrap();
+                        for (_, taint_analyzer) in taint_analyzers
+                            .iter_mut()
+                            .filter(|(analyzer_flag, _)| *analyzer_flag == flag)
+                        {
+                            let idx =
+                                taint_analyzer.mark_sink_at(id.index(), &block_kill, &block_source);
+                            sinks.push((st.span, flag, id.index(), idx));
+                        }
+                    } else if let Some(pname) = paths::UNINIT_VALUE_LIST.contains(self.rcx, name) {
+                        let flag = *UNINIT_MAP.get(pname).unwrap();
+                        if self.uninit_value_is_invalid(flag, generics) {
+                            log::trace!("Found invalid uninitialized value: {pname} (block: {id})");
+                            direct_flag |= flag;
+                            self.status.exposures.push(st.span);
+                        }
+                    }
+                }
+                for (flag, taint_analyzer) in taint_analyzers.iter_mut() {
+                    taint_analyzer.mark_source(id.index(), &(block_source & *flag));
+                    taint_analyzer.mark_kill(id.index(), &block_kill);
+                }
+            }
+
+            self.status.behavior_flag = direct_flag;
+            for (flag, taint_analyzer) in taint_analyzers.iter() {
+                self.status.behavior_flag |= taint_analyzer.propagate() & *flag;
+                // Only the sinks reached by the uninitialized memory are exposures
+                let view_taints = taint_analyzer.sink_view_taints();
+                for (span, sink_flag, node, idx) in sinks.iter() {
+                    if sink_flag == flag && !(view_taints[*node][*idx] & *flag).is_empty() {
+                        self.status.exposures.push(*span);
+                    }
+                }
+            }
+            self.status
+        }
+
+        /// Collects the kind of each uninitialized memory source, with the
+        /// local holding the memory.
+        fn uninit_sources(&self) -> Vec<(BehaviorFlag, VarId)> {
+            let mut sources = Vec::new();
+            for block in self.body.iter() {
+                for st in &block.statements {
+                    if let RawStatement::Call(Call {
+                        func:
+                            FnOperand::Regular(FnPtr {
+                                func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                                ..
+                            }),
+                        dest,
+                        ..
+                    }) = &st.content
+                    {
+                        let pname =
+                            self.rcx
+                                .crate_data
+                                .fun_decls
+                                .get(*callee_did)
+                                .and_then(|decl| {
+                                    paths::UNINIT_SOURCE_LIST
+                                        .contains(self.rcx, &decl.item_meta.name)
+                                });
+                        if let Some(pname) = pname {
+                            sources.push((*UNINIT_MAP.get(pname).unwrap(), dest.var_id));
+                        }
+                    }
+                }
+            }
+            sources
+        }
+
+        /// Returns the kinds of uninitialized memory initialized by the write
+        /// `pname`: the kinds whose sources all hold the memory the written
+        /// pointer is derived from. The memory of a kind can't be initialized
+        /// for only some of its sources.
+        fn initialized(
+            &self,
+            local_defs: &LocalDefs,
+            uninit_sources: &[(BehaviorFlag, VarId)],
+            pname: &str,
+            args: &[Operand],
+        ) -> BehaviorFlag {
+            let written = match INIT_WRITE_DEST_MAP
+                .get(pname)
+                .and_then(|&idx| args.get(idx))
+                .and_then(|arg| local_defs.operand_root(arg))
+            {
+                Some(root) => local_defs.origins(root),
+                None => return BehaviorFlag::empty(),
+            };
+            let mut kill = BehaviorFlag::empty();
+            for flag in (BehaviorFlag::VEC_SET_LEN | BehaviorFlag::ASSUME_INIT).iter() {
+                let mut sources = uninit_sources
+                    .iter()
+                    .filter(|(source_flag, _)| *source_flag == flag)
+                    .peekable();
+                if sources.peek().is_some() && sources.all(|(_, owner)| written.contains(owner)) {
+                    kill |= flag;
+                }
+            }
+            kill
+        }
+
+        /// Return true if the value created by `mem::uninitialized`/`mem::zeroed`
+        /// needs drop or may be invalid.
+        fn uninit_value_is_invalid(&self, flag: BehaviorFlag, generics: &GenericArgs) -> bool {
+            let ty = match generics.types.get(0.into()) {
+                Some(ty) => ty,
+                None => return false,
+            };
+            let invalid = if flag == BehaviorFlag::MEM_ZEROED {
+                self.rcx.zero_is_invalid(ty)
+            } else {
+                self.rcx.has_niche(ty)
+            };
+            invalid || self.rcx.needs_drop(ty, self.generics)
+        }
+    }
+}
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BehaviorFlag: u8 {
+        // `Vec::set_len` on the memory returned by `Vec::with_capacity`
+        const VEC_SET_LEN = 0b00000001;
+        // `MaybeUninit::assume_init` on `MaybeUninit::uninit`
+        const ASSUME_INIT = 0b00000010;
+        // `mem::uninitialized` on a type with drop glue or invalid bit patterns
+        const MEM_UNINITIALIZED = 0b00000100;
+        // `mem::zeroed` on a type with drop glue or for which zero is invalid
+        const MEM_ZEROED = 0b00001000;
+    }
+}
+
+impl IntoReportLevel for BehaviorFlag {
+    fn report_level(&self) -> ReportLevel {
+        let high =
+            BehaviorFlag::VEC_SET_LEN | BehaviorFlag::ASSUME_INIT | BehaviorFlag::MEM_UNINITIALIZED;
+        let med = BehaviorFlag::MEM_ZEROED;
+
+        if !(*self & high).is_empty() {
+            ReportLevel::Error
+        } else if !(*self & med).is_empty() {
+            ReportLevel::Warning
+        } else {
+            ReportLevel::Info
+        }
+    }
+}
@@ -3,35 +3,37 @@ use rustc_middle::mir::Operand;
 use rustc_middle::ty::{Instance, ParamEnv, TyKind};
 use rustc_span::{Span, DUMMY_SP};*/
 
-use crate::rudra::context::{CtxOwner, RudraCtxt};
+use crate::rudra::context::{generics_have_unresolved, CtxOwner, RudraCtxt};
 use snafu::{Backtrace, Snafu};
 use termcolor::Color;
 
 //use crate::prelude::*;
-use crate::rudra::graph::GraphTaint;
+use crate::rudra::graph::NodeTaint;
 use crate::rudra::report::rudra_report;
 use crate::rudra::{
-    analysis::{AnalysisError, AnalysisErrorKind, AnalysisKind, IntoReportLevel},
+    analysis::{vec_set_len_to_0, AnalysisError, AnalysisErrorKind, AnalysisKind, IntoReportLevel},
     graph::TaintAnalyzer,
     paths::{self, *},
     report::{Report, ReportLevel},
-    utils,
-    //visitor::ContainsUnsafe,
+    utils::{self, LabelKind},
 };
 use bitflags::bitflags;
 use if_chain::if_chain;
 
+use crate::rudra::local_defs::{LocalDef, LocalDefs};
 use crate::rudra::macros::unwrap_or;
 use charon_lib::ast::meta::Span;
+use charon_lib::ast::names::Name;
 use charon_lib::formatter::{Formatter, IntoFormatter};
 use charon_lib::gast::{Body, BodyId, FunDeclId};
 use charon_lib::ids::Vector;
 use charon_lib::name_matcher::Pattern;
-use charon_lib::types::{GenericArgs, Ty};
+use charon_lib::types::{GenericArgs, GenericParams, TraitRef, Ty};
 use charon_lib::ullbc_ast::{
-    BodyContents, Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, Literal, Operand,
-    RawConstantExpr, RawStatement, ScalarValue, TraitRefKind,
+    BinOp, BodyContents, Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, Operand,
+    RawStatement, Rvalue, VarId,
 };
+use std::collections::HashSet;
 use tracing::{error, info, warn};
 
 #[derive(Debug, Snafu)]
@@ -52,6 +54,14 @@ impl AnalysisError for UnsafeDataflowError {
     }
 }
 
+/// The decisions of the analysis on a function, for `--explain`
+pub struct DataflowExplanation {
+    pub lines: Vec<String>,
+    pub behavior_flag: BehaviorFlag,
+    /// The level of the report, if `behavior_flag` is not empty
+    pub report_level: ReportLevel,
+}
+
 #[derive(Clone, Copy)]
 pub struct UnsafeDataflowChecker<'tcx> {
     rcx: RudraCtxt<'tcx>,
@@ -67,9 +77,9 @@ impl<'tcx> UnsafeDataflowChecker<'tcx> {
         for decl in self.rcx.crate_data.fun_decls.iter() {
             if let Some(status) = inner::UnsafeDataflowBodyAnalyzer::analyze_body(self.rcx, decl) {
                 let behavior_flag = status.behavior_flag();
-                if !behavior_flag.is_empty()
-                    && behavior_flag.report_level() >= self.rcx.report_level()
-                {
+                let exposure = self.rcx.api_exposure(decl.def_id);
+                let report_level = exposure.adjust(behavior_flag.report_level());
+                if !behavior_flag.is_empty() && report_level >= self.rcx.report_level() {
                     let mut color_span = if let Some(span) =
                         utils::ColorSpan::new(&self.rcx.crate_data, decl.item_meta.span)
                     {
@@ -78,31 +88,82 @@ impl<'tcx> UnsafeDataflowChecker<'tcx> {
                         continue;
                     };
 
-                    for &span in status.strong_bypass_spans() {
-                        color_span.add_sub_span(Color::Red, span);
+                    for (span, path) in status.strong_bypass_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Red,
+                            *span,
+                            format!("strong lifetime bypass: {}", path),
+                        );
                     }
 
-                    for &span in status.weak_bypass_spans() {
-                        color_span.add_sub_span(Color::Yellow, span);
+                    for (span, path) in status.weak_bypass_spans() {
+                        color_span.add_label(
+                            LabelKind::Primary,
+                            Color::Yellow,
+                            *span,
+                            format!("weak lifetime bypass: {}", path),
+                        );
                     }
 
                     for &span in status.unresolvable_generic_function_spans() {
-                        color_span.add_sub_span(Color::Cyan, span);
+                        color_span.add_label(
+                            LabelKind::Secondary,
+                            Color::Cyan,
+                            span,
+                            "may call user code",
+                        );
+                    }
+
+                    for &span in status.user_closure_spans() {
+                        color_span.add_label(
+                            LabelKind::Secondary,
+                            Color::Magenta,
+                            span,
+                            "calls a user-provided closure",
+                        );
                     }
 
-                    rudra_report(Report::with_color_span(
-                        behavior_flag.report_level(),
-                        AnalysisKind::UnsafeDataflow(behavior_flag),
-                        format!(
-                            "Potential unsafe dataflow issue in `{}`",
-                            self.rcx.crate_data.into_fmt().format_object(decl.def_id)
-                        ),
-                        &color_span,
-                    ))
+                    rudra_report(
+                        Report::with_color_span(
+                            report_level,
+                            AnalysisKind::UnsafeDataflow(behavior_flag),
+                            format!(
+                                "Potential unsafe dataflow issue in `{}`",
+                                self.rcx.crate_data.into_fmt().format_object(decl.def_id)
+                            ),
+                            &color_span,
+                        )
+                        .with_exposure(exposure),
+                    )
                 }
             }
         }
     }
+
+    /// Returns the decisions of the analysis on `decl` and the final taint
+    /// of each block, or `None` if its body is not analyzed.
+    pub fn explain(self, decl: &FunDecl) -> Option<DataflowExplanation> {
+        inner::UnsafeDataflowBodyAnalyzer::analyze_body_with(self.rcx, decl, true).map(|status| {
+            let behavior_flag = status.behavior_flag();
+            DataflowExplanation {
+                lines: status.explanation().to_vec(),
+                behavior_flag,
+                report_level: self
+                    .rcx
+                    .api_exposure(decl.def_id)
+                    .adjust(behavior_flag.report_level()),
+            }
+        })
+    }
+
+    /// Returns the taint state of the blocks of `decl`, or `None` if its body
+    /// is not analyzed.
+    pub fn node_taints(self, decl: &FunDecl) -> Option<Vec<NodeTaint>> {
+        inner::UnsafeDataflowBodyAnalyzer::analyze_body(self.rcx, decl)
+            .map(|status| status.node_taints().to_vec())
+            .filter(|taints| !taints.is_empty())
+    }
 }
 
 mod inner {
@@ -110,10 +171,15 @@ mod inner {
 
     #[derive(Debug, Default)]
     pub struct UnsafeDataflowStatus {
-        strong_bypasses: Vec<Span>,
-        weak_bypasses: Vec<Span>,
+        /// The bypass calls with the short path of the bypass
+        strong_bypasses: Vec<(Span, String)>,
+        weak_bypasses: Vec<(Span, String)>,
         unresolvable_generic_functions: Vec<Span>,
+        user_closures: Vec<Span>,
         behavior_flag: BehaviorFlag,
+        node_taints: Vec<NodeTaint>,
+        /// The decisions of the analysis, only recorded for `--explain`
+        explanation: Vec<String>,
     }
 
     impl UnsafeDataflowStatus {
@@ -121,42 +187,74 @@ mod inner {
             self.behavior_flag
         }
 
-        pub fn strong_bypass_spans(&self) -> &Vec<Span> {
+        pub fn strong_bypass_spans(&self) -> &Vec<(Span, String)> {
             &self.strong_bypasses
         }
 
-        pub fn weak_bypass_spans(&self) -> &Vec<Span> {
+        pub fn weak_bypass_spans(&self) -> &Vec<(Span, String)> {
             &self.weak_bypasses
         }
 
         pub fn unresolvable_generic_function_spans(&self) -> &Vec<Span> {
             &self.unresolvable_generic_functions
         }
+
+        pub fn user_closure_spans(&self) -> &Vec<Span> {
+            &self.user_closures
+        }
+
+        pub fn node_taints(&self) -> &[NodeTaint] {
+            &self.node_taints
+        }
+
+        pub fn explanation(&self) -> &[String] {
+            &self.explanation
+        }
     }
 
     pub struct UnsafeDataflowBodyAnalyzer<'a, 'tcx> {
         rcx: RudraCtxt<'tcx>,
+        /// The generic parameters of the analyzed function
+        generics: &'a GenericParams,
         body: &'a BodyContents,
         status: UnsafeDataflowStatus,
-        ptr_read_set: PathSet,
-        ptr_write_set: PathSet,
+        /// Functions moving a value out of a place without invalidating it
+        move_out_set: PathSet,
         vec_set_len: Pattern,
+        vec_len: Pattern,
+        /// Record the decisions of the analysis in `status.explanation`
+        explain: bool,
     }
 
     impl<'a, 'tcx> UnsafeDataflowBodyAnalyzer<'a, 'tcx> {
-        fn new(rcx: RudraCtxt<'tcx>, body: &'a BodyContents) -> Self {
+        fn new(rcx: RudraCtxt<'tcx>, generics: &'a GenericParams, body: &'a BodyContents) -> Self {
             UnsafeDataflowBodyAnalyzer {
                 rcx,
+                generics,
                 body,
                 status: Default::default(),
-                ptr_read_set: PathSet::new(&[&PTR_READ[..], &PTR_DIRECT_READ[..]]),
-                ptr_write_set: PathSet::new(&[&PTR_WRITE[..], &PTR_DIRECT_WRITE[..]]),
+                move_out_set: PathSet::new(&[
+                    &PTR_READ,
+                    &PTR_DIRECT_READ,
+                    &INTRINSICS_COPY,
+                    &INTRINSICS_COPY_NONOVERLAPPING,
+                ]),
                 vec_set_len: Pattern::parse(&crate::rudra::paths::slice_to_string(&VEC_SET_LEN))
                     .unwrap(),
+                vec_len: Pattern::parse(&crate::rudra::paths::slice_to_string(&VEC_LEN)).unwrap(),
+                explain: false,
             }
         }
 
         pub fn analyze_body(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> Option<UnsafeDataflowStatus> {
+            Self::analyze_body_with(rcx, decl, false)
+        }
+
+        pub fn analyze_body_with(
+            rcx: RudraCtxt<'tcx>,
+            decl: &FunDecl,
+            explain: bool,
+        ) -> Option<UnsafeDataflowStatus> {
             let path_discovery_set = PathSet::new(&[
                 &["rudra_paths_discovery"],
                 &["PathsDiscovery"],
@@ -180,38 +278,37 @@ mod inner {
                 // Special case for paths discovery
                 trace_calls_in_body(rcx, body);
                 None
-            }
-            // We don't check if there is unsafe code
-            else
-            /*if ContainsUnsafe::contains_unsafe(rcx.tcx(), body_id)*/
-            {
-                /*match rcx.translate_body(body_did).as_ref() {
-                        Err(e) => {
-                            // MIR is not available for def - log it and continue
-                            e.log();
-                            None
-                        }
-                        Ok(body) => {
-                            let body_analyzer = UnsafeDataflowBodyAnalyzer::new(rcx, body);
-                            Some(body_analyzer.analyze())
-                        }
-                }*/
-                let body_analyzer =
-                    UnsafeDataflowBodyAnalyzer::new(rcx, &body.as_unstructured().unwrap().body);
+            } else if rcx.analyzes_body(decl) {
+                let mut body_analyzer = UnsafeDataflowBodyAnalyzer::new(
+                    rcx,
+                    &decl.signature.generics,
+                    &body.as_unstructured().unwrap().body,
+                );
+                body_analyzer.explain = explain;
                 Some(body_analyzer.analyze())
-            } /*else {
-                  // We don't perform interprocedural analysis,
-                  // thus safe functions are considered safe
-                  Some(Default::default())
-              }*/
+            } else {
+                // We don't perform interprocedural analysis,
+                // thus safe functions are considered safe
+                None
+            }
         }
 
         fn analyze(mut self) -> UnsafeDataflowStatus {
             let mut taint_analyzer = TaintAnalyzer::new(self.body);
+            // Same sources, with the calls to user closures as the only sinks
+            let mut closure_taint_analyzer = TaintAnalyzer::new(self.body);
             let fmt = &self.rcx.crate_data.into_fmt();
             use charon_lib::pretty::FmtWithCtx;
-
-            for (id, block) in self.body.iter_indexed() {
+            let local_defs = LocalDefs::new(self.body);
+            let duplicates = self.duplicates(&local_defs);
+
+            let body = self.body;
+            for (id, block) in body.iter_indexed() {
+                // The statements are visited in order: a sink sees the bypasses
+                // of the preceding statements which are not killed yet, and the
+                // incoming taint without the preceding kills.
+                let mut block_source = BehaviorFlag::empty();
+                let mut block_kill = BehaviorFlag::empty();
                 for st in &block.statements {
                     match &st.content {
                         RawStatement::Call(Call {
@@ -242,56 +339,143 @@ mod inner {
                                 log::trace!(
                                     "Found potential strong lifetime bypass: {name_str} (block: {id})"
                                 );
-                                if self.fn_called_on_copy(*callee_did, generics, &self.ptr_read_set)
-                                {
-                                    // read on Copy types is not a lifetime bypass.
+                                let on_copy = self.fn_called_on_copy(pname, generics);
+                                self.explain(|| {
+                                    format!("bb{id}: call `{name_str}`: strong bypass `{pname}`, fn_called_on_copy: {on_copy}")
+                                });
+                                if on_copy {
+                                    // reading or copying Copy types is not a lifetime bypass.
                                     continue;
                                 }
 
-                                if self.vec_set_len.matches(&self.rcx.crate_data, name)
-                                    && vec_set_len_to_0(args)
-                                {
-                                    // Leaking data is safe (`vec.set_len(0);`)
+                                let leaked_vec =
+                                    if self.vec_set_len.matches(&self.rcx.crate_data, name) {
+                                        self.vec_set_len_leaks(&local_defs, args)
+                                    } else {
+                                        None
+                                    };
+                                if let Some(vec) = leaked_vec {
+                                    // Leaking data is safe (`vec.set_len(0);`), and shrinking
+                                    // the vector after moving elements out of it prevents them
+                                    // from being dropped twice.
+                                    let kill = leak_safe_kill(&duplicates, |duplicate| {
+                                        duplicate.source_origins.contains(&vec)
+                                    });
+                                    log::trace!(
+                                        "Found leak-safe set_len: {name_str} (block: {id})"
+                                    );
+                                    self.explain(|| {
+                                        format!("bb{id}: `set_len` leaks or shrinks the vector: kills {kill:?}")
+                                    });
+                                    block_source.remove(kill);
+                                    block_kill |= kill;
                                     continue;
                                 }
                                 log::trace!(
                                     "Found strong lifetime bypass: {name_str} (block: {id})"
                                 );
 
-                                taint_analyzer
-                                    .mark_source(id.index(), STRONG_BYPASS_MAP.get(pname).unwrap());
-                                self.status.strong_bypasses.push(st.span);
+                                let flag = *STRONG_BYPASS_MAP.get(pname).unwrap();
+                                self.explain(|| format!("bb{id}: source {flag:?}"));
+                                block_source |= flag;
+                                self.status
+                                    .strong_bypasses
+                                    .push((st.span, short_path(pname)));
                             } else if let Some(pname) =
                                 paths::WEAK_LIFETIME_BYPASS_LIST.contains(self.rcx, name)
                             {
-                                if self.fn_called_on_copy(
-                                    *callee_did,
-                                    generics,
-                                    &self.ptr_write_set,
-                                ) {
-                                    // writing Copy types is not a lifetime bypass.
+                                let on_copy = self.fn_called_on_copy(pname, generics);
+                                self.explain(|| {
+                                    format!("bb{id}: call `{name_str}`: weak bypass `{pname}`, fn_called_on_copy: {on_copy}")
+                                });
+                                if on_copy {
+                                    // writing or borrowing Copy types is not a lifetime bypass.
                                     continue;
                                 }
                                 log::trace!("Found weak lifetime bypass: {name_str} (block: {id})");
 
-                                taint_analyzer
-                                    .mark_source(id.index(), WEAK_BYPASS_MAP.get(pname).unwrap());
-                                self.status.weak_bypasses.push(st.span);
-                            } else if paths::GENERIC_FN_LIST.contains(self.rcx, name).is_some() {
+                                let flag = *WEAK_BYPASS_MAP.get(pname).unwrap();
+                                self.explain(|| format!("bb{id}: source {flag:?}"));
+                                block_source |= flag;
+                                self.status.weak_bypasses.push((st.span, short_path(pname)));
+                            } else if let Some(kill) =
+                                self.forgets_duplicate(&local_defs, &duplicates, name, args)
+                            {
+                                // Forgetting either the source or the duplicate of a
+                                // `ptr::read` prevents the value from being dropped twice.
+                                log::trace!("Found leak-safe operation: {name_str} (block: {id})");
+                                self.explain(|| {
+                                    format!("bb{id}: call `{name_str}`: leak-safe operation on a duplicated value, kills {kill:?}")
+                                });
+                                block_source.remove(kill);
+                                block_kill |= kill;
+                            } else if let Some(pname) =
+                                paths::GENERIC_FN_LIST.contains(self.rcx, name)
+                            {
                                 log::trace!(
                                     "Found unresolvable generic function: {name_str} (block: {id})"
                                 );
-                                taint_analyzer.mark_sink(id.index());
+                                self.explain(|| {
+                                    format!(
+                                        "bb{id}: call `{name_str}`: generic function `{pname}`, sink"
+                                    )
+                                });
+                                taint_analyzer.mark_unwinding_sink_at(
+                                    id.index(),
+                                    &block_kill,
+                                    &block_source,
+                                );
                                 self.status.unresolvable_generic_functions.push(st.span);
+                            } else if self.calls_user_closure(None, generics) {
+                                log::trace!(
+                                    "Found call with a user closure: {name_str} (block: {id})"
+                                );
+                                self.explain(|| {
+                                    format!("bb{id}: call `{name_str}`: takes a user closure, sink")
+                                });
+                                for analyzer in [&mut taint_analyzer, &mut closure_taint_analyzer] {
+                                    analyzer.mark_unwinding_sink_at(
+                                        id.index(),
+                                        &block_kill,
+                                        &block_source,
+                                    );
+                                }
+                                self.status.user_closures.push(st.span);
                             } else {
                                 // Check for unresolvable generic function calls
                                 // Check if one of the trait obligations resolves to a clause
-                                if generics_have_unresolved(generics) {
+                                let closures_may_call_user_code =
+                                    self.closures_may_call_user_code(&local_defs, args);
+                                if generics_have_unresolved(generics) || closures_may_call_user_code
+                                {
                                     log::trace!(
                                         "Found call with unresolvable generic parts: {name_str} (block: {id})"
                                     );
-                                    taint_analyzer.mark_sink(id.into());
+                                    // The callee may only unwind if it panics or calls
+                                    // the code of its generic arguments
+                                    let may_unwind = closures_may_call_user_code
+                                        || self.rcx.fn_may_call_user_code(*callee_did, false);
+                                    if may_unwind {
+                                        taint_analyzer.mark_unwinding_sink_at(
+                                            id.index(),
+                                            &block_kill,
+                                            &block_source,
+                                        );
+                                    } else {
+                                        taint_analyzer.mark_sink_at(
+                                            id.index(),
+                                            &block_kill,
+                                            &block_source,
+                                        );
+                                    }
+                                    self.explain(|| {
+                                        format!("bb{id}: call `{name_str}`: unresolved generics or local closure, sink, may unwind: {may_unwind}")
+                                    });
                                     self.status.unresolvable_generic_functions.push(st.span);
+                                } else {
+                                    self.explain(|| {
+                                        format!("bb{id}: call `{name_str}`: no match, resolved generics, not a sink")
+                                    });
                                 }
 
                                 /*match Instance::resolve(
@@ -323,69 +507,201 @@ mod inner {
                                     func: FunIdOrTraitMethodRef::Trait(tref, item_name, ..),
                                     generics,
                                 }),
+                            args,
                             ..
                         }) => {
-                            let is_impl_with_unresolved = match &tref.kind {
-                                TraitRefKind::TraitImpl(_, impl_generics) => {
-                                    generics_have_unresolved(impl_generics)
+                            if self.calls_user_closure(Some(tref), generics) {
+                                log::trace!(
+                                    "Found call to a user closure: {item_name} (block: {id})"
+                                );
+                                self.explain(|| {
+                                    format!("bb{id}: call to trait method `{item_name}`: user closure, sink")
+                                });
+                                for analyzer in [&mut taint_analyzer, &mut closure_taint_analyzer] {
+                                    analyzer.mark_unwinding_sink_at(
+                                        id.index(),
+                                        &block_kill,
+                                        &block_source,
+                                    );
                                 }
-                                _ => true,
-                            };
-                            if is_impl_with_unresolved || generics_have_unresolved(generics) {
-                                // Call contains unresolvable generic parts
+                                self.status.user_closures.push(st.span);
+                            } else if self
+                                .rcx
+                                .trait_call_may_call_user_code(tref, item_name, generics)
+                                || self.closures_may_call_user_code(&local_defs, args)
+                            {
+                                // Call may run user-provided code, or panic
                                 // Here, we are making a two step approximation:
                                 // 1. Unresolvable generic code is potentially user-provided
                                 // 2. User-provided code potentially panics
                                 log::trace!(
                                 "Found unresolvable call to trait method: {item_name} (block: {id})"
                             );
-                                taint_analyzer.mark_sink(id.into());
+                                self.explain(|| {
+                                    format!("bb{id}: call to trait method `{item_name}`: may call user code, sink")
+                                });
+                                taint_analyzer.mark_unwinding_sink_at(
+                                    id.index(),
+                                    &block_kill,
+                                    &block_source,
+                                );
                                 self.status.unresolvable_generic_functions.push(st.span);
+                            } else {
+                                self.explain(|| {
+                                    format!("bb{id}: call to trait method `{item_name}`: resolved, not a sink")
+                                });
                             }
                         }
                         _ => (),
                     }
                 }
+                for analyzer in [&mut taint_analyzer, &mut closure_taint_analyzer] {
+                    analyzer.mark_source(id.index(), &block_source);
+                    analyzer.mark_kill(id.index(), &block_kill);
+                }
             }
 
-            self.status.behavior_flag = taint_analyzer.propagate();
+            if self.explain {
+                let taint_state = taint_analyzer.taint_state();
+                for (id, taint) in taint_state.iter().enumerate() {
+                    let sink = match (taint_analyzer.is_sink(id), taint_analyzer.can_unwind(id)) {
+                        (true, true) => ", unwinding sink",
+                        (true, false) => ", sink",
+                        _ => "",
+                    };
+                    self.status
+                        .explanation
+                        .push(format!("bb{id}: final taint {taint:?}{sink}"));
+                }
+            }
+
+            // A bypassed value is only dropped twice if a sink unwinds while it is tainted
+            self.status.behavior_flag = taint_analyzer.propagate_unwinding();
+            if !closure_taint_analyzer.propagate_unwinding().is_empty() {
+                self.status.behavior_flag |= BehaviorFlag::USER_CLOSURE;
+            }
+            self.status.node_taints = taint_analyzer.node_taints();
             self.status
         }
 
-        fn fn_called_on_copy(
-            &self,
-            callee_did: FunDeclId,
-            generics: &GenericArgs,
-            paths: &PathSet,
-        ) -> bool {
-            if let Some(decl) = self.rcx.crate_data.fun_decls.get(callee_did) {
-                if paths.contains(self.rcx, &decl.item_meta.name).is_some() {
-                    // Just check the first type argument
-                    return self.rcx.is_copyable(generics.types.get(0.into()).unwrap());
-                }
-            }
-            false
-
-            /*for path in paths.iter() {
-                if ext.match_def_path(callee_did, path) {
-                    for arg in callee_args.iter() {
-                        if_chain! {
-                            if let Operand::Move(place) = arg;
-                            let place_ty = place.ty(self.body, tcx);
-                            if let TyKind::RawPtr(ty_and_mut) = place_ty.ty.kind();
-                            let pointed_ty = ty_and_mut.ty;
-                            if pointed_ty.is_copy_modulo_regions(tcx.at(DUMMY_SP), self.param_env);
-                            then {
-                                return true;
-                            }
+        /// Collects the values moved out of a place with `ptr::read` or `copy`.
+        /// The Copy values are not bypasses and are ignored.
+        fn duplicates(&self, local_defs: &LocalDefs) -> Vec<Duplicate> {
+            let mut duplicates = Vec::new();
+            for block in self.body.iter() {
+                for st in &block.statements {
+                    if_chain! {
+                        if let RawStatement::Call(Call {
+                            func:
+                                FnOperand::Regular(FnPtr {
+                                    func: FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)),
+                                    generics,
+                                }),
+                            args,
+                            dest,
+                        }) = &st.content;
+                        if let Some(decl) = self.rcx.crate_data.fun_decls.get(*callee_did);
+                        if let Some(pname) = self.move_out_set.contains(self.rcx, &decl.item_meta.name);
+                        if !self.fn_called_on_copy(pname, generics);
+                        then {
+                            let source = args.first().and_then(|arg| local_defs.operand_root(arg));
+                            duplicates.push(Duplicate {
+                                flag: *STRONG_BYPASS_MAP.get(pname).unwrap(),
+                                locals: source.into_iter().chain([dest.var_id]).collect(),
+                                source_origins: source
+                                    .map(|source| local_defs.origins(source))
+                                    .unwrap_or_default(),
+                            });
                         }
-                        // No need to inspect beyond first arg of the
-                        // target bypass functions.
-                        break;
                     }
                 }
             }
-            false*/
+            duplicates
+        }
+
+        /// Returns the taint killed by a leak-safe operation (`mem::forget`,
+        /// `ManuallyDrop::new`) on the source or the destination of a duplicate,
+        /// or `None` if the call doesn't forget a duplicated value.
+        fn forgets_duplicate(
+            &self,
+            local_defs: &LocalDefs,
+            duplicates: &[Duplicate],
+            name: &Name,
+            args: &[Operand],
+        ) -> Option<BehaviorFlag> {
+            paths::LEAK_SAFE_LIST.contains(self.rcx, name)?;
+            let forgotten = args.first().and_then(|arg| local_defs.operand_root(arg))?;
+            let kill = leak_safe_kill(duplicates, |duplicate| {
+                duplicate.locals.contains(&forgotten)
+            });
+            Some(kill).filter(|kill| !kill.is_empty())
+        }
+
+        /// Returns the vector of a `Vec::set_len()` call leaking elements: the
+        /// new length is 0, or `len() - c` on the same vector. The elements past
+        /// the new length are no longer owned by the vector.
+        fn vec_set_len_leaks(&self, local_defs: &LocalDefs, args: &[Operand]) -> Option<VarId> {
+            let vec = args.first().and_then(|arg| local_defs.operand_root(arg))?;
+            if vec_set_len_to_0(args) {
+                return Some(vec);
+            }
+            if_chain! {
+                if let Some(new_len) = args.get(1);
+                if let Some(LocalDef::Assign(Rvalue::BinaryOp(
+                    BinOp::Sub | BinOp::CheckedSub,
+                    len,
+                    Operand::Const(_),
+                ))) = local_defs.value_def(new_len);
+                if let Some(LocalDef::Call(Call {
+                    func:
+                        FnOperand::Regular(FnPtr {
+                            func: FunIdOrTraitMethodRef::Fun(FunId::Regular(len_did)),
+                            ..
+                        }),
+                    args: len_args,
+                    ..
+                })) = local_defs.value_def(len);
+                if let Some(decl) = self.rcx.crate_data.fun_decls.get(*len_did);
+                if self.vec_len.matches(&self.rcx.crate_data, &decl.item_meta.name);
+                if len_args.first().and_then(|arg| local_defs.operand_root(arg)) == Some(vec);
+                then {
+                    return Some(vec);
+                }
+            }
+            None
+        }
+
+        /// Records a decision of the analysis for `--explain`
+        fn explain(&mut self, line: impl FnOnce() -> String) {
+            if self.explain {
+                self.status.explanation.push(line());
+            }
+        }
+
+        /// Return true if the call runs a closure provided by the caller of the
+        /// analyzed function, or passes it to the callee (e.g. `Option::map(x, f)`).
+        fn calls_user_closure(&self, tref: Option<&TraitRef>, generics: &GenericArgs) -> bool {
+            tref.into_iter()
+                .chain(generics.trait_refs.iter())
+                .any(|tref| self.rcx.is_user_closure(tref))
+        }
+
+        /// Return true if one of the closures of the crate passed to the call
+        /// may panic or run user-provided code. The closures are assumed to be
+        /// called by the callee.
+        fn closures_may_call_user_code(&self, local_defs: &LocalDefs, args: &[Operand]) -> bool {
+            args.iter()
+                .flat_map(|arg| local_defs.closures(arg))
+                .any(|closure_id| self.rcx.fn_may_call_user_code(closure_id, false))
+        }
+
+        /// Return true if the bypass `pname` is applied to a Copy element type
+        /// (see `BYPASS_ELEMENT_TY_MAP`).
+        fn fn_called_on_copy(&self, pname: &str, generics: &GenericArgs) -> bool {
+            BYPASS_ELEMENT_TY_MAP
+                .get(pname)
+                .and_then(|&idx| generics.types.get(idx.into()))
+                .map_or(false, |ty| self.rcx.is_copyable(ty, self.generics))
         }
     }
 
@@ -410,26 +726,46 @@ mod inner {
         }
     }
 
-    // Check if the argument of `Vec::set_len()` is 0_usize.
-    fn vec_set_len_to_0(args: &[Operand]) -> bool {
-        for arg in args.iter() {
-            if let Operand::Const(x) = arg {
-                if let RawConstantExpr::Literal(Literal::Scalar(ScalarValue::Usize(x))) = &x.value {
-                    if *x == 0 {
-                        // Leaking(`vec.set_len(0);`) is safe.
-                        return true;
-                    }
-                }
+    /// A value moved out of a place by `ptr::read` or `copy`
+    struct Duplicate {
+        flag: BehaviorFlag,
+        /// The locals holding the source and the duplicate of the value
+        locals: HashSet<VarId>,
+        /// The locals the pointer to the source is derived from
+        source_origins: HashSet<VarId>,
+    }
+
+    /// Returns the flags of `LEAK_SAFE_KILL` whose duplicates are all covered
+    /// by a leak-safe operation: the taint of a flag can't be killed for only
+    /// some of the values it originates from.
+    fn leak_safe_kill(
+        duplicates: &[Duplicate],
+        covers: impl Fn(&Duplicate) -> bool,
+    ) -> BehaviorFlag {
+        let mut kill = BehaviorFlag::empty();
+        for flag in LEAK_SAFE_KILL.iter() {
+            let mut flag_duplicates = duplicates
+                .iter()
+                .filter(|duplicate| duplicate.flag == flag)
+                .peekable();
+            if flag_duplicates.peek().is_some() && flag_duplicates.all(&covers) {
+                kill |= flag;
             }
         }
-        false
+        kill
+    }
+
+    // Shortens a bypass path for the report labels (`core::ptr::read` -> `ptr::read`).
+    fn short_path(path: &str) -> String {
+        let segments: Vec<&str> = path.split("::").filter(|seg| *seg != "_").collect();
+        segments[segments.len().saturating_sub(2)..].join("::")
     }
 }
 
 // Unsafe Dataflow BypassKind.
 // Used to associate each Unsafe-Dataflow bug report with its cause.
 bitflags! {
-    #[derive(Default, Debug, Clone, Copy)]
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
     pub struct BehaviorFlag: u16 {
         const READ_FLOW = 0b00000001;
         const COPY_FLOW = 0b00000010;
@@ -440,9 +776,15 @@ bitflags! {
         const SLICE_UNCHECKED = 0b01000000;
         const SLICE_FROM_RAW = 0b10000000;
         const VEC_SET_LEN = 0b100000000;
+        // A bypass reaches a call to a closure provided by the caller
+        const USER_CLOSURE = 0b1000000000;
     }
 }
 
+/// The taints removed by the leak-safe operations (`mem::forget`, `ManuallyDrop`,
+/// `set_len` shrinking the vector)
+const LEAK_SAFE_KILL: BehaviorFlag = BehaviorFlag::READ_FLOW.union(BehaviorFlag::COPY_FLOW);
+
 impl IntoReportLevel for BehaviorFlag {
     fn report_level(&self) -> ReportLevel {
         use BehaviorFlag as Flag;
@@ -459,25 +801,3 @@ impl IntoReportLevel for BehaviorFlag {
         }
     }
 }
-
-impl GraphTaint for BehaviorFlag {
-    fn is_empty(&self) -> bool {
-        self.is_all()
-    }
-
-    fn contains(&self, taint: &Self) -> bool {
-        self.contains(*taint)
-    }
-
-    fn join(&mut self, taint: &Self) {
-        *self |= *taint;
-    }
-}
-
-/// Return true if some trait refs are not resolved (they link to clauses)
-fn generics_have_unresolved(generics: &GenericArgs) -> bool {
-    generics
-        .trait_refs
-        .iter()
-        .any(|tr| !matches!(&tr.kind, TraitRefKind::TraitImpl(..)))
-}
@@ -0,0 +1,343 @@
+//! Unsafe impl auditor: lists the local impls of `Send`, `Sync`, `GlobalAlloc`
+//! and of the other unsafe traits, with the bounds of their generic
+//! parameters. The impls of the safe `Unpin` trait are listed too. A
+//! `Send`/`Sync` impl whose parameter is not bounded by the implemented trait
+//! is reported as a warning, except for the `Sync` impls requiring `Send` on a
+//! type which never hands out `&T` (`unsafe impl<T: Send> Sync for Mutex<T>`).
+
+use bitflags::bitflags;
+
+use crate::rudra::analysis::{AnalysisKind, IntoReportLevel};
+use crate::rudra::context::{clause_trait_ref, RudraCtxt};
+use crate::rudra::iter::LocalTraitIter;
+use crate::rudra::paths::{self, *};
+use crate::rudra::report::{rudra_report, Report, ReportLevel};
+use crate::rudra::utils;
+
+use charon_lib::formatter::{FmtCtx, IntoFormatter};
+use charon_lib::types::{
+    RefKind, TraitDecl, TraitDeclId, TraitImpl, TraitImplId, Ty, TyKind, TypeId, TypeVarId,
+};
+
+pub struct UnsafeImplChecker<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+}
+
+impl<'tcx> UnsafeImplChecker<'tcx> {
+    pub fn new(rcx: RudraCtxt<'tcx>) -> Self {
+        UnsafeImplChecker { rcx }
+    }
+
+    pub fn analyze(self) {
+        let mut audited_impls: Vec<(TraitImplId, BehaviorFlag)> = Vec::new();
+        for tdecl in self.rcx.crate_data.trait_decls.iter() {
+            let audited = paths::AUDITED_TRAIT_LIST.contains(self.rcx, &tdecl.item_meta.name);
+            let impl_kind = match audited {
+                Some(pname) => *UNSAFE_IMPL_MAP.get(pname).unwrap(),
+                None => BehaviorFlag::UNSAFE_TRAIT_IMPL,
+            };
+            if impl_kind == BehaviorFlag::UNSAFE_TRAIT_IMPL && !self.is_unsafe_trait(tdecl) {
+                continue;
+            }
+            // Iterate over the impls of the trait
+            for impl_id in LocalTraitIter::new(self.rcx, tdecl.def_id) {
+                if matches!(
+                    self.rcx.crate_data.trait_impls.get(impl_id),
+                    Some(timpl) if timpl.item_meta.is_local
+                ) {
+                    audited_impls.push((impl_id, impl_kind));
+                }
+            }
+        }
+        // The impls of a trait are not ordered
+        audited_impls.sort_by_key(|(impl_id, _)| *impl_id);
+
+        let fmt = self.rcx.crate_data.into_fmt();
+        for (impl_id, impl_kind) in audited_impls {
+            let timpl = &self.rcx.crate_data.trait_impls[impl_id];
+            let (params, missing_bound) = self.describe_params(&fmt, timpl, impl_kind);
+            let behavior_flag = if missing_bound {
+                impl_kind | BehaviorFlag::MISSING_BOUND
+            } else {
+                impl_kind
+            };
+            if behavior_flag.report_level() < self.rcx.report_level() {
+                continue;
+            }
+
+            let color_span = match utils::ColorSpan::new(&self.rcx.crate_data, timpl.item_meta.span)
+            {
+                Some(span) => span,
+                None => continue,
+            };
+            // `Unpin` is a safe trait
+            let header = if impl_kind == BehaviorFlag::UNPIN_IMPL {
+                "Impl"
+            } else {
+                "Unsafe impl"
+            };
+            rudra_report(Report::with_color_span(
+                behavior_flag.report_level(),
+                AnalysisKind::UnsafeImpl(behavior_flag),
+                format!(
+                    "{} `{}`; generic parameters: {}",
+                    header,
+                    fmt.format_object(impl_id),
+                    params
+                ),
+                &color_span,
+            ))
+        }
+    }
+
+    /// Only the impls of an unsafe trait are `unsafe impl`s. Charon doesn't
+    /// record the unsafety of a trait declaration: the unsafe traits of the
+    /// standard library are listed, and the declaration of a local trait is
+    /// looked up in the source code.
+    fn is_unsafe_trait(&self, tdecl: &TraitDecl) -> bool {
+        if paths::UNSAFE_TRAIT_LIST
+            .contains(self.rcx, &tdecl.item_meta.name)
+            .is_some()
+        {
+            return true;
+        }
+        tdecl.item_meta.is_local && {
+            let source = utils::span_to_snippet(&self.rcx.crate_data, &tdecl.item_meta.span)
+                .map_or(String::new(), |lines| lines.join("\n"));
+            let trait_words = header_words(&source);
+            trait_words.starts_with(&["unsafe", "trait"])
+                || trait_words.starts_with(&["unsafe", "auto", "trait"])
+        }
+    }
+
+    /// Returns true unless no local function taking a reference to the type
+    /// of the impl returns a shared reference to its parameter `var_id`,
+    /// as `Mutex::lock` returns a guard instead of a `&T`.
+    fn hands_out_shared(&self, timpl: &TraitImpl, var_id: TypeVarId) -> bool {
+        let self_ty = match timpl.impl_trait.generics.types.get(0.into()) {
+            Some(ty) => ty,
+            None => return true,
+        };
+        let (adt_id, position) = match self_ty.kind() {
+            TyKind::Adt(TypeId::Adt(adt_id), args) => {
+                let position = args
+                    .types
+                    .iter()
+                    .position(|ty| matches!(ty.kind(), TyKind::TypeVar(id) if *id == var_id));
+                match position {
+                    Some(position) => (*adt_id, position),
+                    None => return true,
+                }
+            }
+            _ => return true,
+        };
+
+        self.rcx
+            .crate_data
+            .fun_decls
+            .iter()
+            .filter(|decl| decl.item_meta.is_local)
+            .any(|decl| {
+                decl.signature.inputs.iter().any(|input| {
+                    let param = match input.kind() {
+                        TyKind::Ref(_, referent, _) => match referent.kind() {
+                            TyKind::Adt(TypeId::Adt(id), args) if *id == adt_id => {
+                                args.types.iter().nth(position)
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    param.map_or(false, |param| {
+                        contains_shared_ref(&decl.signature.output, param)
+                    })
+                })
+            })
+    }
+
+    /// The kind of the impls of an audited trait
+    fn audited_kind(&self, trait_id: TraitDeclId) -> Option<BehaviorFlag> {
+        let tdecl = self.rcx.crate_data.trait_decls.get(trait_id)?;
+        let pname = paths::AUDITED_TRAIT_LIST.contains(self.rcx, &tdecl.item_meta.name)?;
+        UNSAFE_IMPL_MAP.get(pname).copied()
+    }
+
+    /// Describes the bounds of each type parameter of the impl. Returns true
+    /// if a parameter of a `Send`/`Sync` impl is not bounded by the trait,
+    /// unless a `Sync` impl requires `Send` on a parameter never handed out
+    /// by reference.
+    fn describe_params(
+        &self,
+        fmt: &FmtCtx,
+        timpl: &TraitImpl,
+        impl_kind: BehaviorFlag,
+    ) -> (String, bool) {
+        let required: Option<TraitDeclId> =
+            if impl_kind.intersects(BehaviorFlag::SEND_IMPL | BehaviorFlag::SYNC_IMPL) {
+                Some(timpl.impl_trait.trait_id)
+            } else {
+                None
+            };
+
+        let mut missing_bound = false;
+        let mut params = Vec::new();
+        for (var_id, var) in timpl.generics.types.iter_indexed() {
+            let bounds: Vec<TraitDeclId> = timpl
+                .generics
+                .trait_clauses
+                .iter()
+                .map(clause_trait_ref)
+                .filter(|trait_ref| {
+                    matches!(
+                        trait_ref.generics.types.get(0.into()).map(|ty| ty.kind()),
+                        Some(TyKind::TypeVar(id)) if *id == var_id
+                    )
+                })
+                .map(|trait_ref| trait_ref.trait_id)
+                .collect();
+
+            let mut description: Vec<String> = bounds
+                .iter()
+                .map(|trait_id| format!("`{}`", fmt.format_object(*trait_id)))
+                .collect();
+            if description.is_empty() {
+                description.push("no bounds".to_owned());
+            }
+            if let Some(required) = required {
+                let send_for_sync = impl_kind.contains(BehaviorFlag::SYNC_IMPL)
+                    && bounds.iter().any(|trait_id| {
+                        self.audited_kind(*trait_id) == Some(BehaviorFlag::SEND_IMPL)
+                    });
+                if send_for_sync
+                    && !bounds.contains(&required)
+                    && !self.hands_out_shared(timpl, var_id)
+                {
+                    // Sound as the wrapper only hands out exclusive access, like `Mutex`
+                    description.push("never handed out by reference".to_owned());
+                } else if !bounds.contains(&required) {
+                    missing_bound = true;
+                    description.push(format!("missing `{}`", fmt.format_object(required)));
+                }
+            }
+            params.push(format!("`{}` ({})", var.name, description.join(", ")));
+        }
+
+        if params.is_empty() {
+            ("none".to_owned(), missing_bound)
+        } else {
+            (params.join(", "), missing_bound)
+        }
+    }
+}
+
+/// The type contains a shared reference to `referent`.
+fn contains_shared_ref(ty: &Ty, referent: &Ty) -> bool {
+    match ty.kind() {
+        TyKind::Ref(_, ty, RefKind::Shared) if ty == referent => true,
+        TyKind::Ref(_, ty, _) => contains_shared_ref(ty, referent),
+        TyKind::Adt(_, args) => args
+            .types
+            .iter()
+            .any(|ty| contains_shared_ref(ty, referent)),
+        _ => false,
+    }
+}
+
+/// The leading words of an item header, without the comments, the
+/// attributes and the visibility: `["unsafe", "impl"]` for
+/// `#[allow(unused)] unsafe impl<T> Send for X<T> {}`. Stops at the first
+/// character which is not part of a word.
+fn header_words(source: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut rest = source;
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("//") {
+            rest = comment.split_once('\n').map_or("", |(_, rest)| rest);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, rest)| rest);
+        } else if let Some(attr) = rest.strip_prefix('#') {
+            let attr = attr.trim_start();
+            let attr = attr.strip_prefix('!').unwrap_or(attr).trim_start();
+            rest = match attr.strip_prefix('[') {
+                Some(attr) => skip_delimited(attr, '[', ']'),
+                None => return words,
+            };
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if len == 0 {
+                return words;
+            }
+            let (word, after) = rest.split_at(len);
+            rest = after;
+            if word == "pub" {
+                // `pub(crate)`
+                if let Some(restriction) = rest.trim_start().strip_prefix('(') {
+                    rest = skip_delimited(restriction, '(', ')');
+                }
+            } else {
+                words.push(word);
+            }
+        }
+    }
+}
+
+/// Skips the source up to the delimiter closing an already opened one. The
+/// delimiters in string literals are ignored.
+fn skip_delimited(source: &str, open: char, close: char) -> &str {
+    let mut depth = 1;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, c) in source.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return &source[idx + c.len_utf8()..];
+            }
+        }
+    }
+    ""
+}
+
+bitflags! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BehaviorFlag: u8 {
+        // `unsafe impl Send`
+        const SEND_IMPL = 0b00000001;
+        // `unsafe impl Sync`
+        const SYNC_IMPL = 0b00000010;
+        // `impl Unpin`
+        const UNPIN_IMPL = 0b00000100;
+        // `unsafe impl GlobalAlloc`
+        const GLOBAL_ALLOC_IMPL = 0b00001000;
+        // Impl of another unsafe trait
+        const UNSAFE_TRAIT_IMPL = 0b00010000;
+        // A type parameter of a `Send`/`Sync` impl is not bounded by the trait
+        const MISSING_BOUND = 0b00100000;
+    }
+}
+
+impl IntoReportLevel for BehaviorFlag {
+    fn report_level(&self) -> ReportLevel {
+        let med = BehaviorFlag::MISSING_BOUND;
+
+        if !(*self & med).is_empty() {
+            ReportLevel::Warning
+        } else {
+            ReportLevel::Info
+        }
+    }
+}
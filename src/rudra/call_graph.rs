@@ -0,0 +1,296 @@
+//! Crate call graph, and the exposure of each function to the public API.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::rudra::graph::Graph;
+use crate::rudra::report::ReportLevel;
+
+use charon_lib::ast::names::{Name, PathElem};
+use charon_lib::gast::FunDeclId;
+use charon_lib::types::{TraitImplId, TraitItemName, TraitRefKind};
+use charon_lib::ullbc_ast::{
+    Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, RawStatement, TranslatedCrate,
+};
+
+/// Returns the method `item_name` of a trait impl: either defined in the impl,
+/// or the default method of the trait.
+pub fn resolve_trait_method(
+    crate_data: &TranslatedCrate,
+    impl_id: TraitImplId,
+    item_name: &TraitItemName,
+) -> Option<FunDeclId> {
+    let timpl = crate_data.trait_impls.get(impl_id)?;
+    timpl
+        .required_methods
+        .iter()
+        .chain(timpl.provided_methods.iter())
+        .find(|(name, _)| name == item_name)
+        .map(|(_, fun_id)| *fun_id)
+}
+
+/// Returns the functions directly called by `decl`. Trait method calls are
+/// only included when the impl is known.
+pub fn callees(crate_data: &TranslatedCrate, decl: &FunDecl) -> Vec<FunDeclId> {
+    let body = match decl
+        .body
+        .as_ref()
+        .ok()
+        .and_then(|body_id| crate_data.bodies.get(*body_id))
+        .and_then(|body| body.as_unstructured())
+    {
+        Some(body) => body,
+        None => return Vec::new(),
+    };
+
+    let mut callees = Vec::new();
+    for block in body.body.iter() {
+        for st in &block.statements {
+            let func = match &st.content {
+                RawStatement::Call(Call {
+                    func: FnOperand::Regular(FnPtr { func, .. }),
+                    ..
+                }) => func,
+                _ => continue,
+            };
+            match func {
+                FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)) => {
+                    callees.push(*callee_did);
+                }
+                FunIdOrTraitMethodRef::Trait(tref, item_name, ..) => {
+                    if let TraitRefKind::TraitImpl(impl_id, _) = &tref.kind {
+                        callees.extend(resolve_trait_method(crate_data, *impl_id, item_name));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    callees
+}
+
+/// The functions of the crate and their direct callees
+pub struct CallGraph {
+    nodes: Vec<FunDeclId>,
+    index: HashMap<FunDeclId, usize>,
+    callees: Vec<Vec<usize>>,
+}
+
+impl CallGraph {
+    pub fn new(crate_data: &TranslatedCrate) -> Self {
+        let nodes: Vec<FunDeclId> = crate_data
+            .fun_decls
+            .iter()
+            .map(|decl| decl.def_id)
+            .collect();
+        let index: HashMap<FunDeclId, usize> =
+            nodes.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let callees = crate_data
+            .fun_decls
+            .iter()
+            .map(|decl| {
+                callees(crate_data, decl)
+                    .iter()
+                    .filter_map(|callee| index.get(callee).copied())
+                    .collect()
+            })
+            .collect();
+        CallGraph {
+            nodes,
+            index,
+            callees,
+        }
+    }
+
+    pub fn node(&self, id: FunDeclId) -> Option<usize> {
+        self.index.get(&id).copied()
+    }
+}
+
+impl Graph for CallGraph {
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn next(&self, id: usize) -> Vec<usize> {
+        self.callees[id].clone()
+    }
+}
+
+/// How a function can be reached from the public API of the crate
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiExposure {
+    /// A public generic function: it may be instantiated with any user type
+    PublicGeneric,
+    /// An exported public function, or a method of a trait impl
+    Public,
+    /// A private function called from the public API
+    Reachable,
+    /// A private function that the public API never calls
+    Private,
+}
+
+impl ApiExposure {
+    /// Raises the level of the reports in public generic functions, and lowers
+    /// the level of the reports in the functions unreachable from the public API.
+    pub fn adjust(self, level: ReportLevel) -> ReportLevel {
+        match (self, level) {
+            (ApiExposure::PublicGeneric, ReportLevel::Info) => ReportLevel::Warning,
+            (ApiExposure::PublicGeneric, _) => ReportLevel::Error,
+            (ApiExposure::Private, ReportLevel::Error) => ReportLevel::Warning,
+            (ApiExposure::Private, _) => ReportLevel::Info,
+            (ApiExposure::Public | ApiExposure::Reachable, level) => level,
+        }
+    }
+}
+
+impl fmt::Display for ApiExposure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            ApiExposure::PublicGeneric => "public generic function",
+            ApiExposure::Public => "public function",
+            ApiExposure::Reachable => "reachable from the public API",
+            ApiExposure::Private => "unreachable from the public API",
+        };
+        f.write_str(description)
+    }
+}
+
+/// The modules declared without `pub` and the names re-exported with
+/// `pub use` in the local source files. Charon doesn't translate the modules,
+/// so their visibility is read from the source code.
+struct ModuleVisibility {
+    private_modules: HashSet<String>,
+    reexported: HashSet<String>,
+}
+
+impl ModuleVisibility {
+    fn new(crate_data: &TranslatedCrate) -> Self {
+        let mut visibility = ModuleVisibility {
+            private_modules: HashSet::new(),
+            reexported: HashSet::new(),
+        };
+        for content in crate_data.file_id_to_content.values() {
+            for line in content.lines() {
+                let line = line.trim_start();
+                if let Some(path) = line.strip_prefix("pub use ") {
+                    // Any name of the path: the item, or a module re-exported
+                    // as a whole or with a glob
+                    visibility
+                        .reexported
+                        .extend(idents(path).map(str::to_owned));
+                } else if let Some((module, false)) = module_decl(line) {
+                    visibility.private_modules.insert(module.to_owned());
+                }
+            }
+        }
+        visibility
+    }
+
+    /// A public item is exported if none of the modules of its path is
+    /// private, unless the private module or the item is re-exported.
+    fn exports(&self, name: &Name) -> bool {
+        let idents: Vec<&str> = name
+            .name
+            .iter()
+            .filter_map(|elem| match elem {
+                PathElem::Ident(ident, _) => Some(ident.as_str()),
+                _ => None,
+            })
+            .collect();
+        // The first name is the crate
+        let (item, modules) = match idents.split_last() {
+            Some((item, path)) => (*item, path.get(1..).unwrap_or_default()),
+            None => return true,
+        };
+        self.reexported.contains(item)
+            || modules.iter().all(|module| {
+                !self.private_modules.contains(*module) || self.reexported.contains(*module)
+            })
+    }
+}
+
+/// The name of the module declared by `line`, and whether it is `pub`.
+/// `pub(crate)` and the other restricted visibilities are private.
+fn module_decl(line: &str) -> Option<(&str, bool)> {
+    let (public, rest) = if let Some(rest) = line.strip_prefix("pub ") {
+        (true, rest)
+    } else if let Some(restriction) = line.strip_prefix("pub(") {
+        (false, restriction.split_once(')')?.1)
+    } else {
+        (false, line)
+    };
+    let module = rest.trim_start().strip_prefix("mod ")?;
+    idents(module).next().map(|module| (module, public))
+}
+
+/// The identifiers of a path, or of a `use` tree.
+fn idents(path: &str) -> impl Iterator<Item = &str> {
+    path.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|ident| !ident.is_empty())
+}
+
+/// Computes the exposure of the local functions. The public API consists of
+/// the exported public functions and of the methods of the trait impls, which
+/// can be called through the trait.
+pub fn api_exposure(crate_data: &TranslatedCrate) -> HashMap<FunDeclId, ApiExposure> {
+    let call_graph = CallGraph::new(crate_data);
+    let module_visibility = ModuleVisibility::new(crate_data);
+
+    let mut entry_points = vec![false; call_graph.len()];
+    for decl in crate_data.fun_decls.iter() {
+        if decl.item_meta.is_local
+            && decl.item_meta.attr_info.public
+            && module_visibility.exports(&decl.item_meta.name)
+        {
+            entry_points[call_graph.node(decl.def_id).unwrap()] = true;
+        }
+    }
+    for timpl in crate_data.trait_impls.iter() {
+        if !timpl.item_meta.is_local {
+            continue;
+        }
+        for (_, fun_id) in timpl.required_methods.iter().chain(&timpl.provided_methods) {
+            if let Some(node) = call_graph.node(*fun_id) {
+                entry_points[node] = true;
+            }
+        }
+    }
+
+    // Breadth-first search from all the entry points
+    let mut reachable = entry_points.clone();
+    let mut work_list: VecDeque<usize> = (0..call_graph.len())
+        .filter(|node| entry_points[*node])
+        .collect();
+    while let Some(node) = work_list.pop_front() {
+        for callee in call_graph.next(node) {
+            if !reachable[callee] {
+                reachable[callee] = true;
+                work_list.push_back(callee);
+            }
+        }
+    }
+
+    crate_data
+        .fun_decls
+        .iter()
+        .filter(|decl| decl.item_meta.is_local)
+        .map(|decl| {
+            let node = call_graph.node(decl.def_id).unwrap();
+            let exposure = if entry_points[node] {
+                if decl.signature.generics.types.is_empty() {
+                    ApiExposure::Public
+                } else {
+                    ApiExposure::PublicGeneric
+                }
+            } else if reachable[node] {
+                ApiExposure::Reachable
+            } else {
+                ApiExposure::Private
+            };
+            (decl.def_id, exposure)
+        })
+        .collect()
+}
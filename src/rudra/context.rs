@@ -214,24 +214,49 @@ impl<'tcx> RudraCtxtOwner<'tcx> {
     }
 }*/
 
+use crate::rudra::call_graph::{self, ApiExposure};
+use crate::rudra::paths;
 use crate::rudra::report::ReportLevel;
+use crate::rudra::visitor::ContainsUnsafe;
+use charon_lib::gast::FunDeclId;
 use charon_lib::name_matcher::NamePattern;
-use charon_lib::types::{RefKind, TraitDeclId, TraitImplId, Ty, TyKind, TypeDeclId, TypeId};
-use charon_lib::ullbc_ast::TranslatedCrate;
+use charon_lib::types::{
+    BuiltinTy, GenericArgs, GenericParams, LiteralTy, RefKind, TraitClause, TraitDeclId,
+    TraitDeclRef, TraitImplId, TraitItemName, TraitRef, TraitRefKind, Ty, TyKind, TypeDeclId,
+    TypeDeclKind, TypeId, TypeVarId,
+};
+use charon_lib::ullbc_ast::{
+    AbortKind, AggregateKind, FieldProjKind, FnOperand, FnPtr, FunDecl, FunId,
+    FunIdOrTraitMethodRef, Locals, Place, ProjectionElem, RawStatement, RawTerminator, Rvalue,
+    TranslatedCrate,
+};
+use parking_lot::Mutex;
 use std::collections::{HashMap, HashSet};
 
 pub struct CtxOwner {
     pub crate_data: TranslatedCrate,
     /// The map from trait decl ids to trait impls
     pub trait_impl_map: HashMap<TraitDeclId, HashSet<TraitImplId>>,
-    /// The set of type ids for which the Copy trait is implemented - note
-    /// that we forget about the precise constraints (this is a first approximation).
-    pub copyable: HashSet<TypeDeclId>,
+    /// The id of the `Copy` trait, if it is used by the crate.
+    pub copy_trait_id: Option<TraitDeclId>,
+    /// The map from type ids to the impls of the Copy trait for this type.
+    /// An impl may be conditional (`impl<T: Copy> Copy for Wrapper<T>`).
+    pub copy_impls: HashMap<TypeDeclId, Vec<TraitImplId>>,
+    /// The types implementing the Drop trait.
+    pub drop_types: HashSet<TypeDeclId>,
     report_level: ReportLevel,
+    /// The exposure of the local functions to the public API
+    api_exposure: HashMap<FunDeclId, ApiExposure>,
+    /// Only analyze the bodies which contain unsafe code
+    unsafe_only: bool,
+    /// Memoized results of `callee_effects`
+    callee_effects: Mutex<HashMap<FunDeclId, CalleeEffects>>,
+    /// The calls of `callee_effects` in progress
+    effects_stack: Mutex<EffectsStack>,
 }
 
 impl CtxOwner {
-    pub fn new(crate_data: TranslatedCrate, report_level: ReportLevel) -> Self {
+    pub fn new(crate_data: TranslatedCrate, report_level: ReportLevel, unsafe_only: bool) -> Self {
         // Compute the trait_impl_map
         let mut trait_impl_map = HashMap::new();
         for tdecl in &crate_data.trait_decls {
@@ -248,7 +273,7 @@ impl CtxOwner {
             }
         }
 
-        // Compute the set of copyable types
+        // Compute the impls of the Copy trait
         let copy_pattern = NamePattern::parse("core::marker::Copy").unwrap();
         let mut copy_trait_id: Vec<_> = crate_data
             .trait_decls
@@ -262,42 +287,75 @@ impl CtxOwner {
             })
             .collect();
         assert!(copy_trait_id.len() <= 1);
-        let copyable = match copy_trait_id.pop() {
-            None => HashSet::new(),
-            Some(copy_trait_id) => crate_data
-                .trait_impls
-                .iter()
-                .filter_map(|t| {
-                    if t.impl_trait.trait_id == copy_trait_id {
-                        match t.impl_trait.generics.types.get(0.into()).unwrap().kind() {
-                            TyKind::Adt(TypeId::Adt(id), _) => Some(*id),
-                            _ => None,
-                        }
-                    } else {
-                        None
+        let copy_trait_id = copy_trait_id.pop();
+        let mut copy_impls: HashMap<TypeDeclId, Vec<TraitImplId>> = HashMap::new();
+        if let Some(copy_trait_id) = copy_trait_id {
+            for t in crate_data.trait_impls.iter() {
+                if t.impl_trait.trait_id != copy_trait_id {
+                    continue;
+                }
+                if let Some(self_ty) = t.impl_trait.generics.types.get(0.into()) {
+                    if let TyKind::Adt(TypeId::Adt(id), _) = self_ty.kind() {
+                        copy_impls.entry(*id).or_default().push(t.def_id);
                     }
-                })
-                .collect(),
-        };
+                }
+            }
+        }
+
+        // Compute the types implementing Drop
+        let drop_pattern = NamePattern::parse("core::ops::drop::Drop").unwrap();
+        let drop_types = crate_data
+            .trait_impls
+            .iter()
+            .filter(|t| {
+                crate_data
+                    .trait_decls
+                    .get(t.impl_trait.trait_id)
+                    .map_or(false, |tdecl| {
+                        drop_pattern.matches(&crate_data, &tdecl.item_meta.name)
+                    })
+            })
+            .filter_map(
+                |t| match t.impl_trait.generics.types.get(0.into())?.kind() {
+                    TyKind::Adt(TypeId::Adt(id), _) => Some(*id),
+                    _ => None,
+                },
+            )
+            .collect();
+
+        let api_exposure = call_graph::api_exposure(&crate_data);
 
         //
         CtxOwner {
             crate_data,
             trait_impl_map,
-            copyable,
+            copy_trait_id,
+            copy_impls,
+            drop_types,
+            api_exposure,
             report_level,
+            unsafe_only,
+            callee_effects: Mutex::new(HashMap::new()),
+            effects_stack: Mutex::new(EffectsStack::default()),
         }
     }
 
-    /// Return true if a type is copyable.
+    /// Return true if a type is copyable, in the context of an item with the
+    /// given generic parameters (a type variable is copyable if it is bounded
+    /// by `Copy` in those parameters).
     /// This is an approximation.
-    pub fn is_copyable(&self, ty: &Ty) -> bool {
+    pub fn is_copyable(&self, ty: &Ty, generics: &GenericParams) -> bool {
         use TyKind::*;
         match ty.kind() {
-            Adt(TypeId::Tuple, args) => args.types.iter().all(|a| self.is_copyable(a)),
-            Adt(TypeId::Adt(id), _) => self.copyable.contains(id),
+            Adt(TypeId::Tuple, args) => args.types.iter().all(|a| self.is_copyable(a, generics)),
+            Adt(TypeId::Adt(id), args) => self.adt_is_copyable(*id, args, generics),
+            // `[T; N]` is Copy if `T` is Copy; `Box`, slices and `str` are never Copy
+            Adt(TypeId::Builtin(BuiltinTy::Array), args) => args
+                .types
+                .get(0.into())
+                .map_or(false, |elem| self.is_copyable(elem, generics)),
             Adt(TypeId::Builtin(_), _) => false,
-            TypeVar(_) => false, // I don't think we need to be more precise
+            TypeVar(id) => self.type_var_is_copy(*id, generics),
             Literal(_) => true,
             Never => false,
             Ref(_, _, r) => *r == RefKind::Shared,
@@ -307,6 +365,298 @@ impl CtxOwner {
             Arrow(..) => false, // true?
         }
     }
+
+    /// Return true if one of the `Copy` impls of the ADT applies to `args`.
+    fn adt_is_copyable(
+        &self,
+        id: TypeDeclId,
+        args: &GenericArgs,
+        generics: &GenericParams,
+    ) -> bool {
+        let impls = match self.copy_impls.get(&id) {
+            Some(impls) => impls,
+            None => return false,
+        };
+        impls.iter().any(|impl_id| {
+            let timpl = match self.crate_data.trait_impls.get(*impl_id) {
+                Some(timpl) => timpl,
+                None => return false,
+            };
+            let impl_args = match timpl
+                .impl_trait
+                .generics
+                .types
+                .get(0.into())
+                .map(|t| t.kind())
+            {
+                Some(TyKind::Adt(_, impl_args)) => impl_args,
+                _ => return false,
+            };
+
+            // Match the self type of the impl (`Wrapper<T>`) against the actual
+            // type arguments, to instantiate the impl type variables.
+            let mut subst: HashMap<TypeVarId, &Ty> = HashMap::new();
+            for (impl_ty, ty) in impl_args.types.iter().zip(args.types.iter()) {
+                match impl_ty.kind() {
+                    TyKind::TypeVar(var) => {
+                        subst.insert(*var, ty);
+                    }
+                    _ if impl_ty == ty => {}
+                    _ => return false,
+                }
+            }
+
+            // Check the `Copy` bounds of the impl on the instantiated variables.
+            // We ignore the other bounds (`Clone` is implied by `Copy`).
+            timpl
+                .generics
+                .trait_clauses
+                .iter()
+                .filter(|clause| Some(clause_trait_ref(clause).trait_id) == self.copy_trait_id)
+                .all(|clause| {
+                    match clause_trait_ref(clause)
+                        .generics
+                        .types
+                        .get(0.into())
+                        .map(|t| t.kind())
+                    {
+                        Some(TyKind::TypeVar(var)) => subst
+                            .get(var)
+                            .map_or(false, |ty| self.is_copyable(ty, generics)),
+                        _ => false,
+                    }
+                })
+        })
+    }
+
+    /// Return true if some bit patterns are invalid for the type (`bool`, `char`,
+    /// references, enums...): a value of this type can't be left uninitialized.
+    /// This is an approximation: type variables are assumed to accept any bit pattern.
+    pub fn has_niche(&self, ty: &Ty) -> bool {
+        self.has_invalid_value(ty, false, &mut HashSet::new())
+    }
+
+    /// Return true if the all-zero bit pattern may be invalid for the type
+    /// (references, `Box`, `NonNull`, function pointers, enums...).
+    pub fn zero_is_invalid(&self, ty: &Ty) -> bool {
+        self.has_invalid_value(ty, true, &mut HashSet::new())
+    }
+
+    fn has_invalid_value(&self, ty: &Ty, zeroed: bool, visited: &mut HashSet<TypeDeclId>) -> bool {
+        use TyKind::*;
+        match ty.kind() {
+            Literal(LiteralTy::Bool | LiteralTy::Char) => !zeroed,
+            Literal(_) => false,
+            Ref(..) | Arrow(..) | Never => true,
+            Adt(TypeId::Builtin(BuiltinTy::Box), _) => true,
+            Adt(TypeId::Builtin(BuiltinTy::Array) | TypeId::Tuple, args) => args
+                .types
+                .iter()
+                .any(|t| self.has_invalid_value(t, zeroed, visited)),
+            Adt(TypeId::Builtin(_), _) => false,
+            Adt(TypeId::Adt(id), _) => {
+                if !visited.insert(*id) {
+                    return false;
+                }
+                let decl = match self.crate_data.type_decls.get(*id) {
+                    Some(decl) => decl,
+                    None => return false,
+                };
+                if paths::NON_NULL_LIST
+                    .contains(self, &decl.item_meta.name)
+                    .is_some()
+                {
+                    return true;
+                }
+                match &decl.kind {
+                    // We don't look at the discriminants: zero may not be one of them
+                    TypeDeclKind::Enum(_) => true,
+                    TypeDeclKind::Struct(fields) => fields
+                        .iter()
+                        .any(|f| self.has_invalid_value(&f.ty, zeroed, visited)),
+                    _ => false,
+                }
+            }
+            TypeVar(_) | RawPtr(..) | TraitType(..) | DynTrait(_) => false,
+        }
+    }
+
+    /// Return true if dropping a value of the type may run code: the type or
+    /// one of its fields implements Drop, in the context of an item with the
+    /// given generic parameters (a type variable needs drop unless it is bounded
+    /// by `Copy`). The foreign types are assumed to need drop, as their Drop
+    /// impls may not be translated.
+    pub fn needs_drop(&self, ty: &Ty, generics: &GenericParams) -> bool {
+        self.needs_drop_in(ty, &[], generics, &mut HashSet::new())
+    }
+
+    /// `subst` holds the generic arguments of the ADTs whose fields are
+    /// visited, the innermost last: the type variables of a field refer to it.
+    fn needs_drop_in(
+        &self,
+        ty: &Ty,
+        subst: &[&GenericArgs],
+        generics: &GenericParams,
+        visited: &mut HashSet<TypeDeclId>,
+    ) -> bool {
+        use TyKind::*;
+        match ty.kind() {
+            Literal(_) | Ref(..) | RawPtr(..) | Arrow(..) | Never => false,
+            Adt(TypeId::Builtin(BuiltinTy::Box), _) => true,
+            Adt(TypeId::Builtin(_) | TypeId::Tuple, args) => args
+                .types
+                .iter()
+                .any(|t| self.needs_drop_in(t, subst, generics, visited)),
+            Adt(TypeId::Adt(id), args) => {
+                if self.drop_types.contains(id) {
+                    return true;
+                }
+                let decl = match self.crate_data.type_decls.get(*id) {
+                    Some(decl) if decl.item_meta.is_local => decl,
+                    _ => return true,
+                };
+                if !visited.insert(*id) {
+                    return false;
+                }
+                // The fields are in the context of the ADT, their type variables
+                // refer to `args`, which are in the context of `subst`
+                let mut field_subst = subst.to_vec();
+                field_subst.push(args);
+                let needs_drop = match &decl.kind {
+                    TypeDeclKind::Struct(fields) => fields
+                        .iter()
+                        .any(|f| self.needs_drop_in(&f.ty, &field_subst, generics, visited)),
+                    TypeDeclKind::Enum(variants) => variants
+                        .iter()
+                        .flat_map(|v| v.fields.iter())
+                        .any(|f| self.needs_drop_in(&f.ty, &field_subst, generics, visited)),
+                    _ => true,
+                };
+                visited.remove(id);
+                needs_drop
+            }
+            TypeVar(id) => match subst.split_last() {
+                Some((args, outer)) => args
+                    .types
+                    .iter()
+                    .nth(id.index())
+                    .map_or(true, |t| self.needs_drop_in(t, outer, generics, visited)),
+                None => !self.type_var_is_copy(*id, generics),
+            },
+            TraitType(..) | DynTrait(_) => true,
+        }
+    }
+
+    /// Return the type of `base.elem`. The field types of an ADT are
+    /// substituted with its generic arguments.
+    pub fn projected_ty(&self, base: &Ty, elem: &ProjectionElem) -> Option<Ty> {
+        match (elem, base.kind()) {
+            (ProjectionElem::Deref, TyKind::Ref(_, ty, _) | TyKind::RawPtr(ty, _)) => {
+                Some(ty.clone())
+            }
+            (ProjectionElem::Deref, TyKind::Adt(TypeId::Builtin(BuiltinTy::Box), args)) => {
+                args.types.get(0.into()).cloned()
+            }
+            (ProjectionElem::Field(FieldProjKind::Tuple(_), field_id), TyKind::Adt(_, args)) => {
+                args.types
+                    .get(TypeVarId::from_usize(field_id.index()))
+                    .cloned()
+            }
+            (ProjectionElem::Field(FieldProjKind::Adt(id, variant_id), field_id), base_kind) => {
+                let fields = match (&self.crate_data.type_decls.get(*id)?.kind, variant_id) {
+                    (TypeDeclKind::Struct(fields), None) => fields,
+                    (TypeDeclKind::Enum(variants), Some(variant_id)) => {
+                        &variants.get(*variant_id)?.fields
+                    }
+                    _ => return None,
+                };
+                let field_ty = fields.get(*field_id)?.ty.clone();
+                match base_kind {
+                    TyKind::Adt(_, args) => Some(field_ty.substitute(args)),
+                    _ => Some(field_ty),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Return true if one of the projections of `place` dereferences a raw pointer.
+    pub fn derefs_raw_ptr(&self, locals: &Locals, place: &Place) -> bool {
+        self.raw_ptr_deref(locals, place).is_some()
+    }
+
+    /// Returns the index of the first projection of `place` dereferencing a
+    /// raw pointer: the projections before it give the raw pointer.
+    pub fn raw_ptr_deref(&self, locals: &Locals, place: &Place) -> Option<usize> {
+        let mut ty: Ty = locals.vars.get(place.var_id)?.ty.clone();
+        for (idx, elem) in place.projection.iter().enumerate() {
+            if matches!(elem, ProjectionElem::Deref) && matches!(ty.kind(), TyKind::RawPtr(..)) {
+                return Some(idx);
+            }
+            ty = self.projected_ty(&ty, elem)?;
+        }
+        None
+    }
+
+    /// Return true if the type variable is bounded by `Copy` in `generics`.
+    fn type_var_is_copy(&self, id: TypeVarId, generics: &GenericParams) -> bool {
+        generics.trait_clauses.iter().any(|clause| {
+            let trait_ref = clause_trait_ref(clause);
+            Some(trait_ref.trait_id) == self.copy_trait_id
+                && matches!(
+                    trait_ref.generics.types.get(0.into()).map(|t| t.kind()),
+                    Some(TyKind::TypeVar(var)) if *var == id
+                )
+        })
+    }
+}
+
+/// The trait implemented by a trait clause, without its bound regions.
+pub fn clause_trait_ref(clause: &TraitClause) -> &TraitDeclRef {
+    &clause.trait_.skip_binder
+}
+
+/// Return true if some trait refs are not resolved (they link to clauses)
+pub fn generics_have_unresolved(generics: &GenericArgs) -> bool {
+    generics.trait_refs.iter().any(|tr| {
+        !matches!(
+            &tr.kind,
+            TraitRefKind::TraitImpl(..) | TraitRefKind::BuiltinOrAuto(..)
+        )
+    })
+}
+
+/// What a call to a function may do, as far as the sinks of the analyses are concerned
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CalleeEffects {
+    /// The function calls a panicking function or fails an assertion
+    pub may_panic: bool,
+    /// The function calls code through its trait clauses: the code depends on
+    /// the generic arguments, which may be provided by the user
+    pub calls_generic_code: bool,
+}
+
+impl CalleeEffects {
+    /// The effects of a function without a body: it may panic and call the
+    /// code of its generic arguments.
+    const UNKNOWN: CalleeEffects = CalleeEffects {
+        may_panic: true,
+        calls_generic_code: true,
+    };
+
+    const PANIC: CalleeEffects = CalleeEffects {
+        may_panic: true,
+        calls_generic_code: false,
+    };
+}
+
+/// The functions whose effects are being computed, to handle the recursive calls
+#[derive(Debug, Default)]
+struct EffectsStack {
+    /// The functions being computed, with the effects assumed for their recursive calls
+    frames: Vec<(FunDeclId, CalleeEffects)>,
+    /// The lowest frame whose assumed effects were used by the current computation
+    lowest_read: Option<usize>,
 }
 
 pub type RudraCtxt<'tcx> = &'tcx CtxOwner;
@@ -315,4 +665,210 @@ impl CtxOwner {
     pub fn report_level(&self) -> ReportLevel {
         self.report_level
     }
+
+    /// The exposure of a local function to the public API
+    pub fn api_exposure(&self, id: FunDeclId) -> ApiExposure {
+        self.api_exposure
+            .get(&id)
+            .copied()
+            .unwrap_or(ApiExposure::Reachable)
+    }
+
+    /// Return true if the body of `decl` should be analyzed: with `unsafe_only`,
+    /// safe functions are considered safe as we don't perform interprocedural analysis.
+    pub fn analyzes_body(&self, decl: &FunDecl) -> bool {
+        !self.unsafe_only || ContainsUnsafe::contains_unsafe(self, decl)
+    }
+
+    /// Return true if a call to the trait method `item_name` may panic or run
+    /// user-provided code. Calls through a trait clause or a trait object run
+    /// code chosen by the caller. When the impl is known, the call is resolved
+    /// to the method of the impl, which is checked instead.
+    pub fn trait_call_may_call_user_code(
+        &self,
+        tref: &TraitRef,
+        item_name: &TraitItemName,
+        generics: &GenericArgs,
+    ) -> bool {
+        match &tref.kind {
+            TraitRefKind::TraitImpl(impl_id, impl_generics) => {
+                let generics_resolved =
+                    !generics_have_unresolved(impl_generics) && !generics_have_unresolved(generics);
+                match call_graph::resolve_trait_method(&self.crate_data, *impl_id, item_name) {
+                    Some(method) => self.fn_may_call_user_code(method, generics_resolved),
+                    None => !generics_resolved,
+                }
+            }
+            // Builtin impls (`Clone` on tuples, `Sized`...) call user code through their generics
+            TraitRefKind::BuiltinOrAuto(..) => generics_have_unresolved(generics),
+            _ => true,
+        }
+    }
+
+    /// Return true if `tref` is the `Fn`/`FnMut`/`FnOnce` bound of a generic
+    /// parameter or a trait object: the closure is provided by the caller.
+    pub fn is_user_closure(&self, tref: &TraitRef) -> bool {
+        if matches!(
+            &tref.kind,
+            TraitRefKind::TraitImpl(..) | TraitRefKind::BuiltinOrAuto(..)
+        ) {
+            return false;
+        }
+        self.crate_data
+            .trait_decls
+            .get(tref.trait_decl_ref.skip_binder.trait_id)
+            .map_or(false, |tdecl| {
+                paths::FN_TRAIT_LIST
+                    .contains(self, &tdecl.item_meta.name)
+                    .is_some()
+            })
+    }
+
+    /// Return true if a call to `fun_id` may panic or run user-provided code.
+    /// If the generic arguments of the call are all resolved, the code called
+    /// through the trait clauses of `fun_id` is known and is not user code.
+    pub fn fn_may_call_user_code(&self, fun_id: FunDeclId, generics_resolved: bool) -> bool {
+        let effects = self.callee_effects(fun_id);
+        effects.may_panic || (effects.calls_generic_code && !generics_resolved)
+    }
+
+    /// Computes the effects of a call to `fun_id` from its body and the effects
+    /// of its callees. The effects of a recursive function are computed by
+    /// iterating from no effect until they are stable.
+    pub fn callee_effects(&self, fun_id: FunDeclId) -> CalleeEffects {
+        if let Some(effects) = self.callee_effects.lock().get(&fun_id) {
+            return *effects;
+        }
+
+        let (depth, outer_lowest_read) = {
+            let mut stack = self.effects_stack.lock();
+            // Recursive call: use the effects assumed so far
+            if let Some(depth) = stack.frames.iter().position(|(id, _)| *id == fun_id) {
+                stack.lowest_read = Some(stack.lowest_read.map_or(depth, |low| low.min(depth)));
+                return stack.frames[depth].1;
+            }
+            stack.frames.push((fun_id, CalleeEffects::default()));
+            (stack.frames.len() - 1, stack.lowest_read.take())
+        };
+
+        let effects = loop {
+            let effects = self.compute_callee_effects(fun_id);
+            let mut stack = self.effects_stack.lock();
+            let recursive = stack.lowest_read.map_or(false, |low| low <= depth);
+            let assumed = &mut stack.frames[depth].1;
+            if !recursive || *assumed == effects {
+                break effects;
+            }
+            *assumed = effects;
+        };
+
+        let mut stack = self.effects_stack.lock();
+        stack.frames.pop();
+        // Only the frames of the callers can still be read
+        let lowest_read = stack.lowest_read.filter(|low| *low < depth);
+        stack.lowest_read = match (outer_lowest_read, lowest_read) {
+            (Some(outer), Some(low)) => Some(outer.min(low)),
+            (outer, low) => outer.or(low),
+        };
+        drop(stack);
+
+        // The effects computed from the assumed effects of a caller are computed
+        // again once the effects of the caller are stable
+        if lowest_read.is_none() {
+            self.callee_effects.lock().insert(fun_id, effects);
+        }
+        effects
+    }
+
+    fn compute_callee_effects(&self, fun_id: FunDeclId) -> CalleeEffects {
+        let body = match self
+            .crate_data
+            .fun_decls
+            .get(fun_id)
+            .and_then(|decl| decl.body.as_ref().ok())
+            .and_then(|body_id| self.crate_data.bodies.get(*body_id))
+            .and_then(|body| body.as_unstructured())
+        {
+            Some(body) => body,
+            None => return CalleeEffects::UNKNOWN,
+        };
+
+        let mut effects = CalleeEffects::default();
+        let mut add_callee = |callee: CalleeEffects, generics_resolved: bool| {
+            effects.may_panic |= callee.may_panic;
+            effects.calls_generic_code |= callee.calls_generic_code && !generics_resolved;
+        };
+        for block in body.body.iter() {
+            if let RawTerminator::Abort(AbortKind::Panic(..)) = &block.terminator.content {
+                add_callee(CalleeEffects::PANIC, true);
+            }
+            for st in &block.statements {
+                let call = match &st.content {
+                    RawStatement::Call(call) => call,
+                    // Bounds and overflow checks
+                    RawStatement::Assert(..) => {
+                        add_callee(CalleeEffects::PANIC, true);
+                        continue;
+                    }
+                    // The closures defined in the body are assumed to be called. They
+                    // share the trait clauses of `fun_id`.
+                    RawStatement::Assign(
+                        _,
+                        Rvalue::Aggregate(AggregateKind::Closure(closure_id, _), _),
+                    ) => {
+                        add_callee(self.callee_effects(*closure_id), false);
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let (func, generics) = match &call.func {
+                    FnOperand::Regular(FnPtr { func, generics }) => (func, generics),
+                    // A function pointer may point to anything
+                    FnOperand::Move(_) => {
+                        add_callee(CalleeEffects::UNKNOWN, false);
+                        continue;
+                    }
+                };
+                match func {
+                    FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)) => {
+                        let name = match self.crate_data.fun_decls.get(*callee_did) {
+                            Some(decl) => &decl.item_meta.name,
+                            None => {
+                                add_callee(CalleeEffects::UNKNOWN, false);
+                                continue;
+                            }
+                        };
+                        let callee = if paths::PANIC_FN_LIST.contains(self, name).is_some() {
+                            CalleeEffects::PANIC
+                        } else if paths::GENERIC_FN_LIST.contains(self, name).is_some() {
+                            CalleeEffects::UNKNOWN
+                        } else {
+                            self.callee_effects(*callee_did)
+                        };
+                        add_callee(callee, !generics_have_unresolved(generics));
+                    }
+                    FunIdOrTraitMethodRef::Trait(tref, item_name, ..) => match &tref.kind {
+                        TraitRefKind::TraitImpl(impl_id, impl_generics) => {
+                            let callee = call_graph::resolve_trait_method(
+                                &self.crate_data,
+                                *impl_id,
+                                item_name,
+                            )
+                            .map_or(CalleeEffects::UNKNOWN, |method| self.callee_effects(method));
+                            let generics_resolved = !generics_have_unresolved(impl_generics)
+                                && !generics_have_unresolved(generics);
+                            add_callee(callee, generics_resolved);
+                        }
+                        TraitRefKind::BuiltinOrAuto(..) => {
+                            add_callee(CalleeEffects::UNKNOWN, !generics_have_unresolved(generics));
+                        }
+                        // Calls through the trait clauses of `fun_id`
+                        _ => add_callee(CalleeEffects::UNKNOWN, false),
+                    },
+                    _ => (),
+                }
+            }
+        }
+        effects
+    }
 }
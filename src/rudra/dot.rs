@@ -0,0 +1,124 @@
+//! Graphviz export of the CFG of a function, for debugging the analyses.
+//! The blocks are labeled with their statements and colored with the taint
+//! state of the unsafe dataflow analysis. The SCCs are drawn as clusters.
+//! Render the output with `dot -Tsvg`.
+
+use std::io::{self, Write};
+
+use crate::rudra::analysis::UnsafeDataflowChecker;
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::graph::{EdgeKind, Graph, NodeTaint, Scc};
+use crate::rudra::utils;
+
+use charon_lib::formatter::{FmtCtx, IntoFormatter};
+use charon_lib::ids::Vector;
+use charon_lib::ullbc_ast::{BlockData, BlockId, FunDecl};
+
+/// Writes the CFG of `decl` as a Graphviz digraph. Returns false if the
+/// function has no body.
+pub fn write_cfg(rcx: RudraCtxt, decl: &FunDecl, writer: &mut impl Write) -> io::Result<bool> {
+    let body = match decl
+        .body
+        .as_ref()
+        .ok()
+        .and_then(|body_id| rcx.crate_data.bodies.get(*body_id))
+        .and_then(|body| body.as_unstructured())
+    {
+        Some(body) => body,
+        None => return Ok(false),
+    };
+    let blocks = &body.body;
+    let fmt = &rcx.crate_data.into_fmt();
+    let name = fmt.format_object(decl.def_id);
+    let taints = UnsafeDataflowChecker::new(rcx).node_taints(decl);
+
+    writeln!(writer, "digraph \"{}\" {{", escape(&name))?;
+    writeln!(writer, "    label=\"{}\";", escape(&name))?;
+    writeln!(
+        writer,
+        "    node [shape=box, fontname=monospace, style=filled];"
+    )?;
+
+    // The SCCs with a cycle are drawn as clusters
+    let scc = Scc::construct(blocks);
+    let mut clustered = vec![false; blocks.len()];
+    for group in 0..scc.num_groups() {
+        let nodes = scc.nodes_in_group(group);
+        let is_cycle = match nodes {
+            [node] => blocks.next(*node).contains(node),
+            _ => true,
+        };
+        if !is_cycle {
+            continue;
+        }
+        writeln!(writer, "    subgraph cluster_{} {{", group)?;
+        writeln!(writer, "        style=dashed;")?;
+        let mut nodes = nodes.to_vec();
+        nodes.sort();
+        for node in nodes {
+            clustered[node] = true;
+            write_block(writer, "        ", blocks, node, taints.as_deref(), fmt)?;
+        }
+        writeln!(writer, "    }}")?;
+    }
+    for node in (0..blocks.len()).filter(|node| !clustered[*node]) {
+        write_block(writer, "    ", blocks, node, taints.as_deref(), fmt)?;
+    }
+
+    for from in 0..blocks.len() {
+        for (to, kind) in blocks.edges(from) {
+            let style = match kind {
+                EdgeKind::Normal => "",
+                EdgeKind::Unwind => " [style=dashed, color=red]",
+                EdgeKind::Abort => " [style=dotted]",
+            };
+            writeln!(writer, "    bb{} -> bb{}{};", from, to, style)?;
+        }
+    }
+    writeln!(writer, "}}")?;
+    Ok(true)
+}
+
+fn write_block(
+    writer: &mut impl Write,
+    indent: &str,
+    blocks: &Vector<BlockId, BlockData>,
+    node: usize,
+    taints: Option<&[NodeTaint]>,
+    fmt: &FmtCtx,
+) -> io::Result<()> {
+    let block = &blocks[BlockId::from_usize(node)];
+    let mut label = format!("bb{}:\\l", node);
+    for line in utils::format_block(block, fmt) {
+        label.push_str(&escape(&line));
+        label.push_str("\\l");
+    }
+
+    let taint = taints.map_or(NodeTaint::default(), |taints| taints[node]);
+    // The sources and the sinks are filled, the tainted blocks have a red border
+    let fill = if taint.source {
+        "salmon"
+    } else if taint.sink {
+        "lightblue"
+    } else {
+        "white"
+    };
+    let border = if taint.tainted {
+        ", color=red, penwidth=2"
+    } else {
+        ""
+    };
+    writeln!(
+        writer,
+        "{}bb{} [label=\"{}\", fillcolor={}{}];",
+        indent, node, label, fill, border
+    )
+}
+
+/// Escapes a string for a double-quoted Graphviz label. The newlines become
+/// left-justified line breaks.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\l")
+}
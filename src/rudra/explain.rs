@@ -0,0 +1,67 @@
+//! Explain mode: prints the body of a function and the decisions of the
+//! unsafe dataflow analysis on it (matched paths, `fn_called_on_copy`, sinks)
+//! with the final taint of each block.
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use crate::rudra::analysis::{AnalysisKind, UnsafeDataflowChecker};
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::utils;
+
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::ullbc_ast::FunDecl;
+
+/// Writes the explanation of the analysis of `decl`. Returns false if the
+/// function has no body.
+pub fn write_explanation(
+    rcx: RudraCtxt,
+    decl: &FunDecl,
+    writer: &mut impl Write,
+) -> io::Result<bool> {
+    let body = match decl
+        .body
+        .as_ref()
+        .ok()
+        .and_then(|body_id| rcx.crate_data.bodies.get(*body_id))
+        .and_then(|body| body.as_unstructured())
+    {
+        Some(body) => body,
+        None => return Ok(false),
+    };
+    let fmt = &rcx.crate_data.into_fmt();
+
+    writeln!(writer, "=== `{}` ===", fmt.format_object(decl.def_id))?;
+    writeln!(writer, "exposure: {}", rcx.api_exposure(decl.def_id))?;
+
+    writeln!(writer, "\n--- Body ---")?;
+    for (id, block) in body.body.iter_indexed() {
+        writeln!(writer, "bb{}:", id)?;
+        for line in utils::format_block(block, fmt) {
+            writeln!(writer, "    {}", line)?;
+        }
+    }
+
+    writeln!(writer, "\n--- UnsafeDataflow ---")?;
+    match UnsafeDataflowChecker::new(rcx).explain(decl) {
+        Some(explanation) => {
+            for line in explanation.lines.iter() {
+                writeln!(writer, "{}", line)?;
+            }
+            if explanation.behavior_flag.is_empty() {
+                writeln!(writer, "result: no report")?;
+            } else {
+                let analyzer: Cow<str> =
+                    AnalysisKind::UnsafeDataflow(explanation.behavior_flag).into();
+                writeln!(
+                    writer,
+                    "result: {:?} {}",
+                    explanation.report_level, analyzer
+                )?;
+            }
+        }
+        None => writeln!(writer, "not analyzed")?,
+    }
+    writeln!(writer)?;
+    Ok(true)
+}
@@ -1,11 +1,31 @@
 use std::{cmp::min, collections::VecDeque};
 
 use charon_lib::ids::Vector;
-use charon_lib::ullbc_ast::{BlockData, BlockId};
+use charon_lib::ullbc_ast::{AbortKind, BlockData, BlockId, RawTerminator};
+
+/// The kind of a control-flow edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// The execution continues normally
+    Normal,
+    /// The edge leads to a panic: the stack unwinds and the live values are dropped
+    Unwind,
+    /// The edge leads to an abort: nothing is dropped
+    Abort,
+}
 
 pub trait Graph {
     fn len(&self) -> usize;
     fn next(&self, id: usize) -> Vec<usize>;
+
+    /// The successors of `id` with the kind of each edge. All the edges are
+    /// normal by default.
+    fn edges(&self, id: usize) -> Vec<(usize, EdgeKind)> {
+        self.next(id)
+            .into_iter()
+            .map(|next| (next, EdgeKind::Normal))
+            .collect()
+    }
 }
 
 impl Graph for Vector<BlockId, BlockData> {
@@ -20,12 +40,71 @@ impl Graph for Vector<BlockId, BlockData> {
             .map(|id| id.index())
             .collect()
     }
+
+    /// ULLBC doesn't have cleanup blocks: the kind of an edge is given by the
+    /// terminator of its target, i.e. edges to a panicking block unwind.
+    fn edges(&self, id: usize) -> Vec<(usize, EdgeKind)> {
+        self[BlockId::from_usize(id)]
+            .targets()
+            .into_iter()
+            .map(|next| {
+                let kind = match &self[next].terminator.content {
+                    RawTerminator::Abort(AbortKind::Panic(..)) => EdgeKind::Unwind,
+                    RawTerminator::Abort(..) => EdgeKind::Abort,
+                    _ => EdgeKind::Normal,
+                };
+                (next.index(), kind)
+            })
+            .collect()
+    }
+}
+
+/// Returns the nodes that can be reached from `start` through at least one edge.
+/// `start` itself is only reachable if it is part of a cycle.
+pub fn reachable_from<G: Graph>(graph: &G, start: usize) -> Vec<bool> {
+    let mut reachable = vec![false; graph.len()];
+    let mut work_list: VecDeque<usize> = graph.next(start).into();
+    while let Some(id) = work_list.pop_front() {
+        if !reachable[id] {
+            reachable[id] = true;
+            work_list.extend(graph.next(id));
+        }
+    }
+    reachable
 }
 
 pub trait GraphTaint: Clone + Default {
     fn is_empty(&self) -> bool;
     fn contains(&self, taint: &Self) -> bool;
     fn join(&mut self, taint: &Self);
+    fn remove(&mut self, taint: &Self);
+}
+
+/// The behavior flags of the analyzers are used as taints
+impl<F: bitflags::Flags + Copy + Default> GraphTaint for F {
+    fn is_empty(&self) -> bool {
+        bitflags::Flags::is_empty(self)
+    }
+
+    fn contains(&self, taint: &Self) -> bool {
+        bitflags::Flags::contains(self, *taint)
+    }
+
+    fn join(&mut self, taint: &Self) {
+        self.insert(*taint);
+    }
+
+    fn remove(&mut self, taint: &Self) {
+        bitflags::Flags::remove(self, *taint);
+    }
+}
+
+/// The state of a node after the propagation, for debugging
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NodeTaint {
+    pub source: bool,
+    pub sink: bool,
+    pub tainted: bool,
 }
 
 pub struct TaintAnalyzer<'a, G: Graph, T: GraphTaint> {
@@ -33,6 +112,14 @@ pub struct TaintAnalyzer<'a, G: Graph, T: GraphTaint> {
     len: usize,
     sources: Vec<T>,
     sinks: Vec<bool>,
+    /// Taints removed from the taint flowing into a node (before its own source is added)
+    kills: Vec<T>,
+    /// Nodes containing a statement that may unwind, e.g. a call to user code
+    unwinds: Vec<bool>,
+    /// The kills and the sources preceding each sink statement of a node, and
+    /// whether the statement may unwind, see `mark_sink_at`. Empty if the sinks
+    /// see the taint flowing out of the node.
+    sink_views: Vec<Vec<(T, T, bool)>>,
 }
 
 impl<'a, G: Graph, T: GraphTaint> TaintAnalyzer<'a, G, T> {
@@ -43,6 +130,9 @@ impl<'a, G: Graph, T: GraphTaint> TaintAnalyzer<'a, G, T> {
             len: graph_len,
             sources: vec![T::default(); graph_len],
             sinks: vec![false; graph_len],
+            kills: vec![T::default(); graph_len],
+            unwinds: vec![false; graph_len],
+            sink_views: vec![Vec::new(); graph_len],
         }
     }
 
@@ -62,18 +152,179 @@ impl<'a, G: Graph, T: GraphTaint> TaintAnalyzer<'a, G, T> {
         self.sinks[id] = true;
     }
 
+    /// Marks a sink statement in the middle of a node: it sees the incoming
+    /// taint without the kills of the preceding statements (`kill`), and the
+    /// sources of the preceding statements which are not killed (`source`).
+    /// Returns the index of the sink in the node, see `sink_view_taints`.
+    pub fn mark_sink_at(&mut self, id: usize, kill: &T, source: &T) -> usize {
+        self.push_sink_view(id, kill, source, false)
+    }
+
+    /// Same as `mark_sink_at`, for a sink statement that may unwind. The other
+    /// sinks of the node are not considered as unwinding.
+    pub fn mark_unwinding_sink_at(&mut self, id: usize, kill: &T, source: &T) -> usize {
+        self.push_sink_view(id, kill, source, true)
+    }
+
+    fn push_sink_view(&mut self, id: usize, kill: &T, source: &T, unwinds: bool) -> usize {
+        self.sinks[id] = true;
+        self.sink_views[id].push((kill.clone(), source.clone(), unwinds));
+        self.sink_views[id].len() - 1
+    }
+
     pub fn unmark_sink(&mut self, id: usize) {
         self.sinks[id] = false;
+        self.sink_views[id].clear();
+    }
+
+    pub fn mark_kill(&mut self, id: usize, taint: &T) {
+        self.kills[id].join(taint);
+    }
+
+    pub fn clear_kill(&mut self, id: usize) {
+        self.kills[id] = T::default();
+    }
+
+    pub fn mark_unwind(&mut self, id: usize) {
+        self.unwinds[id] = true;
     }
 
-    // Unmark all sources and sinks
+    // Unmark all sources, sinks, kills and unwinding nodes
     pub fn clear(&mut self) {
         self.sources = vec![T::default(); self.len];
         self.sinks = vec![false; self.len];
+        self.kills = vec![T::default(); self.len];
+        self.unwinds = vec![false; self.len];
+        self.sink_views = vec![Vec::new(); self.len];
     }
 
     // Checks reachability between `self.sources` & `self.sinks`.
     pub fn propagate(&self) -> T {
+        let sink_taints = self.sink_taints();
+
+        // Join all taints in the sink nodes
+        let mut ret = T::default();
+        for id in 0..self.len {
+            if self.sinks[id] && !sink_taints[id].is_empty() {
+                ret.join(&sink_taints[id]);
+            }
+        }
+
+        return ret;
+    }
+
+    /// Same as `propagate`, but only the sinks that can unwind are considered:
+    /// the sink statements marked with `mark_unwinding_sink_at`, the sinks of
+    /// the nodes marked with `mark_unwind`, and the nodes with an unwinding edge.
+    pub fn propagate_unwinding(&self) -> T {
+        let taint_state = self.taint_state();
+        let view_taints = self.view_taints(&taint_state);
+
+        let mut ret = T::default();
+        for id in 0..self.len {
+            if !self.sinks[id] {
+                continue;
+            }
+            let node_unwinds = self.unwinds[id] || self.has_unwind_edge(id);
+            if self.sink_views[id].is_empty() {
+                if node_unwinds {
+                    ret.join(&taint_state[id]);
+                }
+                continue;
+            }
+            for ((_, _, unwinds), taint) in self.sink_views[id].iter().zip(&view_taints[id]) {
+                if *unwinds || node_unwinds {
+                    ret.join(taint);
+                }
+            }
+        }
+        ret
+    }
+
+    /// The taint seen by the sinks of each node: the taint flowing out of the
+    /// node, or the taint at the sink statements marked with `mark_sink_at`.
+    fn sink_taints(&self) -> Vec<T> {
+        let taint_state = self.taint_state();
+        let view_taints = self.view_taints(&taint_state);
+
+        (0..self.len)
+            .map(|id| {
+                if self.sink_views[id].is_empty() {
+                    return taint_state[id].clone();
+                }
+                let mut ret = T::default();
+                for taint in view_taints[id].iter() {
+                    ret.join(taint);
+                }
+                ret
+            })
+            .collect()
+    }
+
+    /// The taint seen by each sink statement marked with `mark_sink_at`,
+    /// indexed by node and by the index returned by `mark_sink_at`.
+    pub fn sink_view_taints(&self) -> Vec<Vec<T>> {
+        self.view_taints(&self.taint_state())
+    }
+
+    fn view_taints(&self, taint_state: &[T]) -> Vec<Vec<T>> {
+        let mut incoming = vec![T::default(); self.len];
+        for id in 0..self.len {
+            for (next, kind) in self.graph.edges(id) {
+                if kind != EdgeKind::Abort {
+                    incoming[next].join(&taint_state[id]);
+                }
+            }
+        }
+
+        (0..self.len)
+            .map(|id| {
+                self.sink_views[id]
+                    .iter()
+                    .map(|(kill, source, _)| {
+                        let mut taint = incoming[id].clone();
+                        taint.remove(kill);
+                        taint.join(source);
+                        taint
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The node contains a sink that may unwind
+    pub fn can_unwind(&self, id: usize) -> bool {
+        self.unwinds[id]
+            || self.sink_views[id].iter().any(|(_, _, unwinds)| *unwinds)
+            || self.has_unwind_edge(id)
+    }
+
+    fn has_unwind_edge(&self, id: usize) -> bool {
+        self.graph
+            .edges(id)
+            .iter()
+            .any(|(_, kind)| *kind == EdgeKind::Unwind)
+    }
+
+    /// Returns the sources, the sinks and the tainted nodes.
+    pub fn node_taints(&self) -> Vec<NodeTaint> {
+        let taint_state = self.taint_state();
+        (0..self.len)
+            .map(|id| NodeTaint {
+                source: !self.sources[id].is_empty(),
+                sink: self.sinks[id],
+                tainted: !taint_state[id].is_empty(),
+            })
+            .collect()
+    }
+
+    pub fn is_sink(&self, id: usize) -> bool {
+        self.sinks[id]
+    }
+
+    /// The taint flowing out of each node. Taints don't flow through the abort
+    /// edges, as the values are not dropped when the process aborts.
+    pub fn taint_state(&self) -> Vec<T> {
         let mut taint_state = vec![T::default(); self.len];
         let mut work_list = VecDeque::new();
 
@@ -87,26 +338,21 @@ impl<'a, G: Graph, T: GraphTaint> TaintAnalyzer<'a, G, T> {
 
         // Breadth-first propagation
         while let Some(current) = work_list.pop_front() {
-            for next in self.graph.next(current) {
-                let mut next_state = std::mem::take(&mut taint_state[next]);
-                let taint = &taint_state[current];
-                if !next_state.contains(taint) {
-                    next_state.join(taint);
+            for (next, kind) in self.graph.edges(current) {
+                if kind == EdgeKind::Abort {
+                    continue;
+                }
+                // The kills of `next` apply to the incoming taint only
+                let mut taint = taint_state[current].clone();
+                taint.remove(&self.kills[next]);
+                if !taint_state[next].contains(&taint) {
+                    taint_state[next].join(&taint);
                     work_list.push_back(next);
                 }
-                taint_state[next] = next_state;
-            }
-        }
-
-        // Join all taints in the sink nodes
-        let mut ret = T::default();
-        for id in 0..self.len {
-            if self.sinks[id] && !taint_state[id].is_empty() {
-                ret.join(&taint_state[id]);
             }
         }
 
-        return ret;
+        taint_state
     }
 }
 
@@ -175,6 +421,11 @@ impl<'a, G: Graph> Scc<'a, G> {
             }
         }
 
+        // groups are numbered from 1 during the construction, 0 meaning "not assigned yet"
+        for group in state.group_of_node.iter_mut() {
+            *group -= 1;
+        }
+
         // collect all inter-group edges
         let num_group = state.nodes_in_group.len();
         let mut group_graph = vec![Vec::new(); num_group];
@@ -247,12 +498,13 @@ impl<'a, G: Graph> Scc<'a, G> {
 
     fn topological_dfs(&self, state: &mut SccTopologicalOrderState, group: usize) {
         state.visited[group] = true;
-        state.order.push(group);
         for &next_group in self.next_groups(group).iter() {
             if !state.visited[next_group] {
                 self.topological_dfs(state, next_group)
             }
         }
+        // post-order: reversed, the groups come before their successors
+        state.order.push(group);
     }
 
     pub fn topological_order(&self) -> Vec<usize> {
@@ -285,4 +537,838 @@ impl<'a, G: Graph> Scc<'a, G> {
     pub fn next_groups(&self, group_idx: usize) -> &[usize] {
         &self.group_graph[group_idx]
     }
+
+    pub fn num_groups(&self) -> usize {
+        self.nodes_in_group.len()
+    }
+}
+
+/// Returns the predecessors of each node.
+pub fn predecessors<G: Graph>(graph: &G) -> Vec<Vec<usize>> {
+    let mut preds = vec![Vec::new(); graph.len()];
+    for from in 0..graph.len() {
+        for to in graph.next(from) {
+            preds[to].push(from);
+        }
+    }
+    preds
+}
+
+/// Returns the nodes reachable from `root`, in reverse post-order.
+fn reverse_post_order<G: Graph>(graph: &G, root: usize) -> Vec<usize> {
+    let mut visited = vec![false; graph.len()];
+    let mut order = Vec::new();
+    // (node, successors not visited yet)
+    let mut stack = vec![(root, graph.next(root))];
+    visited[root] = true;
+    while let Some((node, successors)) = stack.last_mut() {
+        match successors.pop() {
+            Some(next) => {
+                if !visited[next] {
+                    visited[next] = true;
+                    let next_successors = graph.next(next);
+                    stack.push((next, next_successors));
+                }
+            }
+            None => {
+                order.push(*node);
+                stack.pop();
+            }
+        }
+    }
+    order.reverse();
+    order
+}
+
+/// Dominator tree of the nodes reachable from a root, computed with the
+/// iterative algorithm of Cooper, Harvey and Kennedy.
+/// `a` dominates `b` if every path from the root to `b` goes through `a`.
+pub struct Dominators {
+    /// Immediate dominator of each node, `None` for the root and the unreachable nodes
+    idom: Vec<Option<usize>>,
+    reachable: Vec<bool>,
+}
+
+impl Dominators {
+    pub fn new<G: Graph>(graph: &G, root: usize) -> Self {
+        let order = reverse_post_order(graph, root);
+        let preds = predecessors(graph);
+
+        // position of each node in `order`
+        let mut rank = vec![usize::MAX; graph.len()];
+        for (i, &node) in order.iter().enumerate() {
+            rank[node] = i;
+        }
+
+        let mut idom = vec![None; graph.len()];
+        idom[root] = Some(root);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in order.iter().skip(1) {
+                let mut new_idom = None;
+                for &pred in preds[node].iter() {
+                    if idom[pred].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred,
+                        Some(current) => intersect(&idom, &rank, pred, current),
+                    });
+                }
+                if new_idom.is_some() && idom[node] != new_idom {
+                    idom[node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let reachable = (0..graph.len()).map(|node| idom[node].is_some()).collect();
+        idom[root] = None;
+        Dominators { idom, reachable }
+    }
+
+    pub fn immediate_dominator(&self, node: usize) -> Option<usize> {
+        self.idom[node]
+    }
+
+    pub fn is_reachable(&self, node: usize) -> bool {
+        self.reachable[node]
+    }
+
+    /// Return true if `a` dominates `b`. Every node dominates itself, and
+    /// the unreachable nodes are not dominated.
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        if !self.reachable[b] {
+            return false;
+        }
+        let mut current = Some(b);
+        while let Some(node) = current {
+            if node == a {
+                return true;
+            }
+            current = self.idom[node];
+        }
+        false
+    }
+
+    /// Returns the dominators of `node`, from `node` itself up to the root.
+    pub fn dominators(&self, node: usize) -> Vec<usize> {
+        if !self.reachable[node] {
+            return Vec::new();
+        }
+        let mut dominators = vec![node];
+        while let Some(idom) = self.idom[*dominators.last().unwrap()] {
+            dominators.push(idom);
+        }
+        dominators
+    }
+}
+
+fn intersect(idom: &[Option<usize>], rank: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while rank[a] > rank[b] {
+            a = idom[a].unwrap();
+        }
+        while rank[b] > rank[a] {
+            b = idom[b].unwrap();
+        }
+    }
+    a
+}
+
+/// The reverse of a graph, with a virtual exit node (the last node) leading
+/// to all the nodes without successors.
+struct ReverseGraph {
+    preds: Vec<Vec<usize>>,
+}
+
+impl ReverseGraph {
+    fn new<G: Graph>(graph: &G) -> Self {
+        let mut preds = predecessors(graph);
+        let exits = (0..graph.len())
+            .filter(|&node| graph.next(node).is_empty())
+            .collect();
+        preds.push(exits);
+        ReverseGraph { preds }
+    }
+}
+
+impl Graph for ReverseGraph {
+    fn len(&self) -> usize {
+        self.preds.len()
+    }
+
+    fn next(&self, id: usize) -> Vec<usize> {
+        self.preds[id].clone()
+    }
+}
+
+/// Post-dominator tree: `a` post-dominates `b` if every path from `b` to an
+/// exit (a node without successors) goes through `a`. The nodes that can't
+/// reach an exit, e.g. infinite loops, are not part of the tree.
+pub struct PostDominators {
+    dominators: Dominators,
+}
+
+impl PostDominators {
+    pub fn new<G: Graph>(graph: &G) -> Self {
+        let reverse = ReverseGraph::new(graph);
+        let exit = graph.len();
+        let mut dominators = Dominators::new(&reverse, exit);
+        // Remove the virtual exit node
+        for idom in dominators.idom.iter_mut() {
+            if *idom == Some(exit) {
+                *idom = None;
+            }
+        }
+        dominators.idom.truncate(exit);
+        dominators.reachable.truncate(exit);
+        PostDominators { dominators }
+    }
+
+    /// The immediate post-dominator of `node`, `None` for the exits and for
+    /// the nodes whose paths lead to several exits.
+    pub fn immediate_post_dominator(&self, node: usize) -> Option<usize> {
+        self.dominators.immediate_dominator(node)
+    }
+
+    /// Return true if `node` can reach an exit.
+    pub fn reaches_exit(&self, node: usize) -> bool {
+        self.dominators.is_reachable(node)
+    }
+
+    /// Return true if `a` post-dominates `b`. Every node post-dominates itself.
+    pub fn post_dominates(&self, a: usize, b: usize) -> bool {
+        self.dominators.dominates(a, b)
+    }
+
+    /// Returns the post-dominators of `node`, from `node` itself to the exit.
+    pub fn post_dominators(&self, node: usize) -> Vec<usize> {
+        self.dominators.dominators(node)
+    }
+}
+
+/// A loop of the loop-nesting forest
+#[derive(Debug, Clone)]
+pub struct Loop {
+    /// The node through which the loop is entered. For irreducible loops,
+    /// which have several entries, the entry with the lowest index.
+    pub header: usize,
+    /// The nodes of the loop, including the nodes of the nested loops, sorted
+    pub nodes: Vec<usize>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+/// Loop-nesting forest: the loops are the non-trivial SCCs of the graph. The
+/// nested loops are the SCCs of a loop once the edges to its header are removed.
+pub struct LoopForest {
+    loops: Vec<Loop>,
+    /// The innermost loop of each node
+    loop_of_node: Vec<Option<usize>>,
+}
+
+/// A subset of the nodes of a graph, without the edges to the removed headers
+struct LoopBody<'a, G: Graph> {
+    graph: &'a G,
+    members: Vec<bool>,
+    header: Option<usize>,
+}
+
+impl<'a, G: Graph> Graph for LoopBody<'a, G> {
+    fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    fn next(&self, id: usize) -> Vec<usize> {
+        if !self.members[id] {
+            return Vec::new();
+        }
+        self.graph
+            .next(id)
+            .into_iter()
+            .filter(|&next| self.members[next] && Some(next) != self.header)
+            .collect()
+    }
+}
+
+impl LoopForest {
+    pub fn new<G: Graph>(graph: &G) -> Self {
+        let mut forest = LoopForest {
+            loops: Vec::new(),
+            loop_of_node: vec![None; graph.len()],
+        };
+        let preds = predecessors(graph);
+        let body = LoopBody {
+            graph,
+            members: vec![true; graph.len()],
+            header: None,
+        };
+        forest.find_loops(&body, &preds, None);
+        forest
+    }
+
+    fn find_loops<G: Graph>(
+        &mut self,
+        body: &LoopBody<G>,
+        preds: &[Vec<usize>],
+        parent: Option<usize>,
+    ) {
+        let scc = Scc::construct(body);
+        for group in 0..scc.num_groups() {
+            let nodes = scc.nodes_in_group(group);
+            let is_loop = match nodes {
+                [node] => body.members[*node] && body.next(*node).contains(node),
+                _ => true,
+            };
+            if !is_loop {
+                continue;
+            }
+
+            let mut nodes = nodes.to_vec();
+            nodes.sort();
+            let in_loop = |node: usize| nodes.binary_search(&node).is_ok();
+            let header = nodes
+                .iter()
+                .copied()
+                .find(|&node| preds[node].iter().any(|&pred| !in_loop(pred)))
+                .unwrap_or(nodes[0]);
+
+            let loop_id = self.loops.len();
+            for &node in nodes.iter() {
+                self.loop_of_node[node] = Some(loop_id);
+            }
+            if let Some(parent) = parent {
+                self.loops[parent].children.push(loop_id);
+            }
+            let mut members = vec![false; body.len()];
+            for &node in nodes.iter() {
+                members[node] = true;
+            }
+            self.loops.push(Loop {
+                header,
+                nodes,
+                parent,
+                children: Vec::new(),
+            });
+
+            let inner_body = LoopBody {
+                graph: body.graph,
+                members,
+                header: Some(header),
+            };
+            self.find_loops(&inner_body, preds, Some(loop_id));
+        }
+    }
+
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    /// The outermost loops
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.loops.len())
+            .filter(|&id| self.loops[id].parent.is_none())
+            .collect()
+    }
+
+    /// The innermost loop containing `node`
+    pub fn innermost_loop(&self, node: usize) -> Option<usize> {
+        self.loop_of_node[node]
+    }
+
+    /// The number of loops containing `node`
+    pub fn depth(&self, node: usize) -> usize {
+        let mut depth = 0;
+        let mut current = self.loop_of_node[node];
+        while let Some(id) = current {
+            depth += 1;
+            current = self.loops[id].parent;
+        }
+        depth
+    }
+
+    pub fn is_header(&self, node: usize) -> bool {
+        self.loop_of_node[node].map_or(false, |id| self.loops[id].header == node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rudra::analysis::UnsafeDataflowBehaviorFlag as Flag;
+
+    /// Adjacency list graph used to build small CFGs by hand.
+    struct TestGraph(Vec<Vec<usize>>);
+
+    impl Graph for TestGraph {
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn next(&self, id: usize) -> Vec<usize> {
+            self.0[id].clone()
+        }
+    }
+
+    fn graph(edges: &[&[usize]]) -> TestGraph {
+        TestGraph(edges.iter().map(|e| e.to_vec()).collect())
+    }
+
+    /// Adjacency list graph with the kind of each edge.
+    struct KindGraph(Vec<Vec<(usize, EdgeKind)>>);
+
+    impl Graph for KindGraph {
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn next(&self, id: usize) -> Vec<usize> {
+            self.0[id].iter().map(|(next, _)| *next).collect()
+        }
+
+        fn edges(&self, id: usize) -> Vec<(usize, EdgeKind)> {
+            self.0[id].clone()
+        }
+    }
+
+    #[test]
+    fn default_flag_is_empty() {
+        assert!(GraphTaint::is_empty(&Flag::default()));
+        assert!(!GraphTaint::is_empty(&Flag::READ_FLOW));
+        assert!(!GraphTaint::is_empty(&Flag::all()));
+    }
+
+    #[test]
+    fn no_source_no_taint() {
+        // 0 -> 1 -> 2
+        let g = graph(&[&[1], &[2], &[]]);
+        let mut analyzer = TaintAnalyzer::<_, Flag>::new(&g);
+        analyzer.mark_sink(2);
+        assert!(analyzer.propagate().is_empty());
+    }
+
+    #[test]
+    fn source_reaches_sink() {
+        // 0 -> 1 -> 2
+        let g = graph(&[&[1], &[2], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_sink(2);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+    }
+
+    #[test]
+    fn source_and_sink_in_same_node() {
+        let g = graph(&[&[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::VEC_SET_LEN);
+        analyzer.mark_sink(0);
+        assert_eq!(analyzer.propagate(), Flag::VEC_SET_LEN);
+    }
+
+    #[test]
+    fn taint_does_not_flow_backwards() {
+        // 0 -> 1 -> 2, the sink runs before the bypass
+        let g = graph(&[&[1], &[2], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(2, &Flag::READ_FLOW);
+        analyzer.mark_sink(0);
+        assert!(analyzer.propagate().is_empty());
+    }
+
+    #[test]
+    fn disconnected_nodes_are_not_tainted() {
+        // 0 -> 1, 2 -> 3
+        let g = graph(&[&[1], &[], &[3], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::COPY_FLOW);
+        analyzer.mark_sink(3);
+        assert!(analyzer.propagate().is_empty());
+
+        analyzer.mark_sink(1);
+        assert_eq!(analyzer.propagate(), Flag::COPY_FLOW);
+    }
+
+    #[test]
+    fn taint_flows_through_loop_back_edge() {
+        // 0 -> 1 -> 2 -> 1, 2 -> 3; the sink is the loop header
+        let g = graph(&[&[1], &[2], &[1, 3], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(2, &Flag::WRITE_FLOW);
+        analyzer.mark_sink(1);
+        assert_eq!(analyzer.propagate(), Flag::WRITE_FLOW);
+    }
+
+    #[test]
+    fn self_loop_terminates() {
+        // 0 -> 0, 0 -> 1
+        let g = graph(&[&[0, 1], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_sink(1);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+    }
+
+    #[test]
+    fn taints_from_several_sources_are_joined() {
+        // 0 -> 2, 1 -> 2, 2 -> 3
+        let g = graph(&[&[2], &[2], &[3], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_source(1, &Flag::TRANSMUTE);
+        analyzer.mark_sink(3);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW | Flag::TRANSMUTE);
+    }
+
+    #[test]
+    fn only_taints_reaching_a_sink_are_reported() {
+        // 0 -> 1 -> 3, 2 -> 3, 1 -> 4
+        let g = graph(&[&[1], &[3, 4], &[3], &[], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_source(2, &Flag::VEC_FROM_RAW);
+        analyzer.mark_sink(4);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+    }
+
+    #[test]
+    fn kill_stops_propagation() {
+        // 0 -> 1 -> 2
+        let g = graph(&[&[1], &[2], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &(Flag::READ_FLOW | Flag::TRANSMUTE));
+        analyzer.mark_kill(1, &Flag::READ_FLOW);
+        analyzer.mark_sink(2);
+        assert_eq!(analyzer.propagate(), Flag::TRANSMUTE);
+
+        analyzer.clear_kill(1);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW | Flag::TRANSMUTE);
+    }
+
+    #[test]
+    fn kill_on_one_path_only() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3
+        let g = graph(&[&[1, 2], &[3], &[3], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_kill(1, &Flag::READ_FLOW);
+        analyzer.mark_sink(3);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+
+        analyzer.mark_kill(2, &Flag::READ_FLOW);
+        assert!(analyzer.propagate().is_empty());
+    }
+
+    #[test]
+    fn kill_does_not_remove_own_source() {
+        // 0 -> 1 -> 2 -> 1: the killing node is also a source
+        let g = graph(&[&[1], &[2], &[1]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(1, &Flag::READ_FLOW);
+        analyzer.mark_kill(1, &Flag::READ_FLOW);
+        analyzer.mark_sink(2);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+    }
+
+    #[test]
+    fn sink_before_kill_in_same_node() {
+        // 0 -> 1: the sink of node 1 precedes its kill
+        let g = graph(&[&[1], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_kill(1, &Flag::READ_FLOW);
+        analyzer.mark_sink_at(1, &Flag::empty(), &Flag::empty());
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+
+        // The kill precedes the sink
+        analyzer.unmark_sink(1);
+        analyzer.mark_sink_at(1, &Flag::READ_FLOW, &Flag::empty());
+        assert!(analyzer.propagate().is_empty());
+    }
+
+    #[test]
+    fn sink_between_source_and_kill_in_same_node() {
+        // source; sink; kill in node 0, then a sink in node 1
+        let g = graph(&[&[1], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_sink_at(0, &Flag::empty(), &Flag::READ_FLOW);
+        analyzer.mark_kill(0, &Flag::READ_FLOW);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+
+        // The source is killed before leaving node 0
+        analyzer.unmark_sink(0);
+        analyzer.mark_sink(1);
+        assert!(analyzer.propagate().is_empty());
+    }
+
+    #[test]
+    fn taint_of_each_sink_in_same_node() {
+        // 0 -> 1: sink; kill; sink in node 1
+        let g = graph(&[&[1], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        let first = analyzer.mark_sink_at(1, &Flag::empty(), &Flag::empty());
+        analyzer.mark_kill(1, &Flag::READ_FLOW);
+        let second = analyzer.mark_sink_at(1, &Flag::READ_FLOW, &Flag::empty());
+
+        let taints = analyzer.sink_view_taints();
+        assert_eq!(taints[1][first], Flag::READ_FLOW);
+        assert!(taints[1][second].is_empty());
+        assert!(taints[0].is_empty());
+    }
+
+    #[test]
+    fn clearing_sources_and_sinks() {
+        let g = graph(&[&[1], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_sink(1);
+
+        analyzer.unmark_sink(1);
+        assert!(analyzer.propagate().is_empty());
+
+        analyzer.mark_sink(1);
+        analyzer.clear_source(0);
+        assert!(analyzer.propagate().is_empty());
+
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_kill(1, &Flag::READ_FLOW);
+        analyzer.clear();
+        assert!(analyzer.propagate().is_empty());
+
+        // Kills are cleared as well
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_sink(1);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+    }
+
+    #[test]
+    fn node_taints_after_propagation() {
+        // 0 -> 1 -> 2, 3
+        let g = graph(&[&[1], &[2], &[], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(1, &Flag::READ_FLOW);
+        analyzer.mark_sink(2);
+        let taints = analyzer.node_taints();
+        assert_eq!(taints[0], NodeTaint::default());
+        assert!(taints[1].source && taints[1].tainted && !taints[1].sink);
+        assert!(taints[2].sink && taints[2].tainted && !taints[2].source);
+        assert!(!taints[3].tainted);
+    }
+
+    #[test]
+    fn edges_are_normal_by_default() {
+        let g = graph(&[&[1, 2], &[], &[]]);
+        assert_eq!(
+            g.edges(0),
+            vec![(1, EdgeKind::Normal), (2, EdgeKind::Normal)]
+        );
+    }
+
+    #[test]
+    fn taint_does_not_flow_through_abort_edges() {
+        // 0 -> 1 (abort), 0 -> 2
+        use EdgeKind::*;
+        let g = KindGraph(vec![vec![(1, Abort), (2, Normal)], vec![], vec![]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_sink(1);
+        assert!(analyzer.propagate().is_empty());
+
+        analyzer.mark_sink(2);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+    }
+
+    #[test]
+    fn only_unwinding_sinks_are_reported() {
+        // 0 -> 1 -> 2: the sink in 1 returns normally
+        let g = graph(&[&[1], &[2], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_sink(1);
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+        assert!(!analyzer.can_unwind(1));
+        assert!(analyzer.propagate_unwinding().is_empty());
+
+        analyzer.mark_unwind(1);
+        assert_eq!(analyzer.propagate_unwinding(), Flag::READ_FLOW);
+
+        analyzer.clear();
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_sink(1);
+        assert!(analyzer.propagate_unwinding().is_empty());
+    }
+
+    #[test]
+    fn only_unwinding_sink_statements_are_reported() {
+        // 0 -> 1: tainted sink that returns normally; kill; unwinding sink in node 1
+        let g = graph(&[&[1], &[]]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_sink_at(1, &Flag::empty(), &Flag::empty());
+        analyzer.mark_kill(1, &Flag::READ_FLOW);
+        analyzer.mark_unwinding_sink_at(1, &Flag::READ_FLOW, &Flag::empty());
+        assert!(analyzer.can_unwind(1));
+        assert_eq!(analyzer.propagate(), Flag::READ_FLOW);
+        assert!(analyzer.propagate_unwinding().is_empty());
+
+        analyzer.clear();
+        analyzer.mark_source(0, &Flag::READ_FLOW);
+        analyzer.mark_unwinding_sink_at(1, &Flag::empty(), &Flag::empty());
+        analyzer.mark_kill(1, &Flag::READ_FLOW);
+        analyzer.mark_sink_at(1, &Flag::READ_FLOW, &Flag::empty());
+        assert_eq!(analyzer.propagate_unwinding(), Flag::READ_FLOW);
+    }
+
+    #[test]
+    fn sink_with_unwind_edge_can_unwind() {
+        // 0 -> 1, 1 -> 2 (unwind), 1 -> 3
+        use EdgeKind::*;
+        let g = KindGraph(vec![
+            vec![(1, Normal)],
+            vec![(2, Unwind), (3, Normal)],
+            vec![],
+            vec![],
+        ]);
+        let mut analyzer = TaintAnalyzer::new(&g);
+        analyzer.mark_source(0, &Flag::COPY_FLOW);
+        analyzer.mark_sink(1);
+        assert!(analyzer.can_unwind(1));
+        assert!(!analyzer.can_unwind(3));
+        assert_eq!(analyzer.propagate_unwinding(), Flag::COPY_FLOW);
+
+        // The sink is only reached once the bypass has been killed
+        analyzer.mark_kill(1, &Flag::COPY_FLOW);
+        assert!(analyzer.propagate_unwinding().is_empty());
+    }
+
+    #[test]
+    fn scc_groups_and_topological_order() {
+        // 0 -> 1 -> 2 -> 1, 2 -> 3, 0 -> 3
+        let g = graph(&[&[1, 3], &[2], &[1, 3], &[]]);
+        let scc = Scc::construct(&g);
+        assert_eq!(scc.num_groups(), 3);
+        assert_eq!(scc.group_of_node(1), scc.group_of_node(2));
+        assert_ne!(scc.group_of_node(0), scc.group_of_node(1));
+
+        let order = scc.topological_order();
+        let position = |node: usize| {
+            order
+                .iter()
+                .position(|&group| group == scc.group_of_node(node))
+                .unwrap()
+        };
+        assert!(position(0) < position(1));
+        assert!(position(1) < position(3));
+    }
+
+    #[test]
+    fn dominators_of_diamond() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3, 3 -> 4
+        let g = graph(&[&[1, 2], &[3], &[3], &[4], &[]]);
+        let dom = Dominators::new(&g, 0);
+        assert_eq!(dom.immediate_dominator(0), None);
+        assert_eq!(dom.immediate_dominator(1), Some(0));
+        assert_eq!(dom.immediate_dominator(3), Some(0));
+        assert_eq!(dom.immediate_dominator(4), Some(3));
+        assert!(dom.dominates(0, 4));
+        assert!(dom.dominates(3, 4));
+        assert!(dom.dominates(4, 4));
+        assert!(!dom.dominates(1, 3));
+        assert_eq!(dom.dominators(4), vec![4, 3, 0]);
+    }
+
+    #[test]
+    fn dominators_with_loop_and_unreachable_node() {
+        // 0 -> 1 -> 2 -> 1, 2 -> 3; 4 -> 3 is unreachable
+        let g = graph(&[&[1], &[2], &[1, 3], &[], &[3]]);
+        let dom = Dominators::new(&g, 0);
+        assert_eq!(dom.immediate_dominator(1), Some(0));
+        assert_eq!(dom.immediate_dominator(2), Some(1));
+        assert_eq!(dom.immediate_dominator(3), Some(2));
+        assert!(!dom.is_reachable(4));
+        assert!(!dom.dominates(4, 3));
+        assert!(!dom.dominates(0, 4));
+        assert!(dom.dominators(4).is_empty());
+    }
+
+    #[test]
+    fn post_dominators_of_diamond() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3, 3 -> 4
+        let g = graph(&[&[1, 2], &[3], &[3], &[4], &[]]);
+        let pdom = PostDominators::new(&g);
+        assert_eq!(pdom.immediate_post_dominator(0), Some(3));
+        assert_eq!(pdom.immediate_post_dominator(1), Some(3));
+        assert_eq!(pdom.immediate_post_dominator(3), Some(4));
+        assert_eq!(pdom.immediate_post_dominator(4), None);
+        assert!(pdom.post_dominates(4, 0));
+        assert!(!pdom.post_dominates(1, 0));
+        assert_eq!(pdom.post_dominators(0), vec![0, 3, 4]);
+    }
+
+    #[test]
+    fn post_dominators_with_several_exits() {
+        // 0 -> 1 -> 2, 1 -> 3, 2 and 3 are exits; 4 -> 4 never exits
+        let g = graph(&[&[1], &[2, 3], &[], &[], &[4]]);
+        let pdom = PostDominators::new(&g);
+        assert_eq!(pdom.immediate_post_dominator(0), Some(1));
+        assert_eq!(pdom.immediate_post_dominator(1), None);
+        assert!(!pdom.post_dominates(2, 1));
+        assert!(pdom.reaches_exit(3));
+        assert!(!pdom.reaches_exit(4));
+    }
+
+    #[test]
+    fn loop_forest_with_nested_loops() {
+        // 0 -> 1 -> 2 -> 3 -> 2, 3 -> 4 -> 1, 4 -> 5
+        let g = graph(&[&[1], &[2], &[3], &[2, 4], &[1, 5], &[]]);
+        let forest = LoopForest::new(&g);
+        assert_eq!(forest.loops().len(), 2);
+        assert_eq!(forest.roots().len(), 1);
+
+        let outer = &forest.loops()[forest.roots()[0]];
+        assert_eq!(outer.header, 1);
+        assert_eq!(outer.nodes, vec![1, 2, 3, 4]);
+        assert_eq!(outer.children.len(), 1);
+
+        let inner = &forest.loops()[outer.children[0]];
+        assert_eq!(inner.header, 2);
+        assert_eq!(inner.nodes, vec![2, 3]);
+
+        assert_eq!(forest.depth(0), 0);
+        assert_eq!(forest.depth(1), 1);
+        assert_eq!(forest.depth(3), 2);
+        assert_eq!(forest.depth(5), 0);
+        assert!(forest.is_header(1));
+        assert!(forest.is_header(2));
+        assert!(!forest.is_header(3));
+        assert_eq!(forest.innermost_loop(4), forest.innermost_loop(1));
+    }
+
+    #[test]
+    fn loop_forest_self_loop() {
+        // 0 -> 1 -> 1, 1 -> 2
+        let g = graph(&[&[1], &[1, 2], &[]]);
+        let forest = LoopForest::new(&g);
+        assert_eq!(forest.loops().len(), 1);
+        assert_eq!(forest.loops()[0].nodes, vec![1]);
+        assert!(forest.is_header(1));
+        assert_eq!(forest.innermost_loop(0), None);
+    }
+
+    #[test]
+    fn reachable_from_follows_edges() {
+        let g = graph(&[&[1], &[2], &[], &[0]]);
+        assert_eq!(reachable_from(&g, 0), vec![false, true, true, false]);
+        assert_eq!(reachable_from(&g, 3), vec![true, true, true, false]);
+        assert_eq!(reachable_from(&g, 2), vec![false; 4]);
+    }
+
+    #[test]
+    fn reachable_from_includes_start_on_cycle() {
+        let g = graph(&[&[1], &[0, 2], &[]]);
+        assert_eq!(reachable_from(&g, 1), vec![true, true, true]);
+    }
 }
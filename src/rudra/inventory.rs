@@ -0,0 +1,257 @@
+//! Unsafe inventory: instead of looking for bugs, count the unsafe operations
+//! of each local function (lifetime bypasses, raw pointer dereferences and
+//! calls to unresolved generic code), with crate-wide totals.
+//! The inventory is written as JSON or CSV, to prioritize audits and to track
+//! the use of unsafe code over time.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::rudra::analysis::UnsafeDataflowBehaviorFlag;
+use crate::rudra::context::{generics_have_unresolved, RudraCtxt};
+use crate::rudra::local_defs;
+use crate::rudra::paths::{self, STRONG_BYPASS_MAP, WEAK_BYPASS_MAP};
+
+use charon_lib::formatter::IntoFormatter;
+use charon_lib::ullbc_ast::{
+    Call, FnOperand, FunDecl, FunId, FunIdOrTraitMethodRef, RawStatement, TraitRefKind,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InventoryFormat {
+    Json,
+    Csv,
+}
+
+/// Unsafe operations of a function, or of the whole crate
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct UnsafeCounts {
+    /// Calls in `STRONG_LIFETIME_BYPASS_LIST`, by bypass category
+    pub strong_bypasses: BTreeMap<String, usize>,
+    /// Calls in `WEAK_LIFETIME_BYPASS_LIST`, by bypass category
+    pub weak_bypasses: BTreeMap<String, usize>,
+    pub raw_ptr_derefs: usize,
+    pub unresolved_generic_calls: usize,
+}
+
+impl UnsafeCounts {
+    fn add(&mut self, other: &UnsafeCounts) {
+        for (category, count) in &other.strong_bypasses {
+            *self.strong_bypasses.entry(category.clone()).or_default() += count;
+        }
+        for (category, count) in &other.weak_bypasses {
+            *self.weak_bypasses.entry(category.clone()).or_default() += count;
+        }
+        self.raw_ptr_derefs += other.raw_ptr_derefs;
+        self.unresolved_generic_calls += other.unresolved_generic_calls;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.strong_bypasses.values().all(|count| *count == 0)
+            && self.weak_bypasses.values().all(|count| *count == 0)
+            && self.raw_ptr_derefs == 0
+            && self.unresolved_generic_calls == 0
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionInventory {
+    pub name: String,
+    pub is_unsafe: bool,
+    #[serde(flatten)]
+    pub counts: UnsafeCounts,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct InventoryTotals {
+    pub functions: usize,
+    pub unsafe_functions: usize,
+    /// Functions with at least one unsafe operation
+    pub functions_with_unsafe_operations: usize,
+    #[serde(flatten)]
+    pub counts: UnsafeCounts,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CrateInventory {
+    pub functions: Vec<FunctionInventory>,
+    pub totals: InventoryTotals,
+}
+
+impl CrateInventory {
+    pub fn collect(rcx: RudraCtxt) -> Self {
+        let mut inventory = CrateInventory::default();
+        for decl in rcx.crate_data.fun_decls.iter() {
+            if !decl.item_meta.is_local {
+                continue;
+            }
+            let function = FunctionInventory {
+                name: rcx.crate_data.into_fmt().format_object(decl.def_id),
+                is_unsafe: decl.signature.is_unsafe,
+                counts: count_unsafe_operations(rcx, decl),
+            };
+
+            let totals = &mut inventory.totals;
+            totals.functions += 1;
+            if function.is_unsafe {
+                totals.unsafe_functions += 1;
+            }
+            if !function.counts.is_empty() {
+                totals.functions_with_unsafe_operations += 1;
+            }
+            totals.counts.add(&function.counts);
+            inventory.functions.push(function);
+        }
+        inventory
+    }
+
+    pub fn write(&self, format: InventoryFormat, writer: &mut impl Write) -> io::Result<()> {
+        match format {
+            InventoryFormat::Json => {
+                serde_json::to_writer_pretty(&mut *writer, self)?;
+                writeln!(writer)
+            }
+            InventoryFormat::Csv => self.write_csv(writer),
+        }
+    }
+
+    /// One row per function, and a last row with the crate-wide totals
+    fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        let strong_categories = bypass_categories(&STRONG_BYPASS_MAP);
+        let weak_categories = bypass_categories(&WEAK_BYPASS_MAP);
+
+        let mut header = vec!["name".to_owned(), "is_unsafe".to_owned()];
+        header.extend(strong_categories.iter().cloned());
+        header.extend(weak_categories.iter().cloned());
+        header.push("raw_ptr_derefs".to_owned());
+        header.push("unresolved_generic_calls".to_owned());
+        writeln!(writer, "{}", header.join(","))?;
+
+        let row = |name: &str, is_unsafe: String, counts: &UnsafeCounts| {
+            let mut row = vec![csv_field(name), is_unsafe];
+            for category in &strong_categories {
+                row.push(counts.strong_bypasses[category].to_string());
+            }
+            for category in &weak_categories {
+                row.push(counts.weak_bypasses[category].to_string());
+            }
+            row.push(counts.raw_ptr_derefs.to_string());
+            row.push(counts.unresolved_generic_calls.to_string());
+            row.join(",")
+        };
+        for function in &self.functions {
+            let is_unsafe = function.is_unsafe.to_string();
+            writeln!(
+                writer,
+                "{}",
+                row(&function.name, is_unsafe, &function.counts)
+            )?;
+        }
+        let unsafe_functions = self.totals.unsafe_functions.to_string();
+        writeln!(
+            writer,
+            "{}",
+            row("TOTAL", unsafe_functions, &self.totals.counts)
+        )
+    }
+}
+
+/// Counts the unsafe operations in the body of `decl`
+fn count_unsafe_operations(rcx: RudraCtxt, decl: &FunDecl) -> UnsafeCounts {
+    let mut counts = UnsafeCounts::default();
+    // Report every category, even when it is not used
+    for category in bypass_categories(&STRONG_BYPASS_MAP) {
+        counts.strong_bypasses.insert(category, 0);
+    }
+    for category in bypass_categories(&WEAK_BYPASS_MAP) {
+        counts.weak_bypasses.insert(category, 0);
+    }
+
+    let body = match decl
+        .body
+        .as_ref()
+        .ok()
+        .and_then(|body_id| rcx.crate_data.bodies.get(*body_id))
+        .and_then(|body| body.as_unstructured())
+    {
+        Some(body) => body,
+        None => return counts,
+    };
+
+    for block in body.body.iter() {
+        for st in &block.statements {
+            counts.raw_ptr_derefs += local_defs::statement_places(&st.content)
+                .iter()
+                .filter(|place| rcx.derefs_raw_ptr(&body.locals, place))
+                .count();
+
+            let func = match &st.content {
+                RawStatement::Call(Call {
+                    func: FnOperand::Regular(func),
+                    ..
+                }) => func,
+                _ => continue,
+            };
+            match &func.func {
+                FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)) => {
+                    let name = match rcx.crate_data.fun_decls.get(*callee_did) {
+                        Some(decl) => &decl.item_meta.name,
+                        None => continue,
+                    };
+                    if let Some(pname) = paths::STRONG_LIFETIME_BYPASS_LIST.contains(rcx, name) {
+                        let category = category_name(*STRONG_BYPASS_MAP.get(pname).unwrap());
+                        *counts.strong_bypasses.entry(category).or_default() += 1;
+                    } else if let Some(pname) = paths::WEAK_LIFETIME_BYPASS_LIST.contains(rcx, name)
+                    {
+                        let category = category_name(*WEAK_BYPASS_MAP.get(pname).unwrap());
+                        *counts.weak_bypasses.entry(category).or_default() += 1;
+                    } else if paths::GENERIC_FN_LIST.contains(rcx, name).is_some()
+                        || generics_have_unresolved(&func.generics)
+                    {
+                        counts.unresolved_generic_calls += 1;
+                    }
+                }
+                FunIdOrTraitMethodRef::Trait(tref, ..) => {
+                    let is_impl_with_unresolved = match &tref.kind {
+                        TraitRefKind::TraitImpl(_, impl_generics) => {
+                            generics_have_unresolved(impl_generics)
+                        }
+                        TraitRefKind::BuiltinOrAuto(..) => false,
+                        _ => true,
+                    };
+                    if is_impl_with_unresolved || generics_have_unresolved(&func.generics) {
+                        counts.unresolved_generic_calls += 1;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    counts
+}
+
+/// The categories of a bypass map, in the order of their flags
+fn bypass_categories(map: &HashMap<String, UnsafeDataflowBehaviorFlag>) -> Vec<String> {
+    let mut flags: Vec<UnsafeDataflowBehaviorFlag> = map.values().copied().collect();
+    flags.sort_by_key(|flag| flag.bits());
+    flags.dedup();
+    flags.into_iter().map(category_name).collect()
+}
+
+/// `READ_FLOW` is reported as `read_flow`
+fn category_name(flag: UnsafeDataflowBehaviorFlag) -> String {
+    flag.iter_names()
+        .next()
+        .map_or("unknown".to_owned(), |(name, _)| name.to_lowercase())
+}
+
+/// Quotes a CSV field if needed: the formatted names contain commas
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
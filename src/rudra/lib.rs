@@ -1,13 +1,26 @@
 use crate::progress_info;
 use crate::rudra::analysis::{
     //SendSyncVarianceChecker,
+    DoubleFreeChecker,
+    DropPanicChecker,
+    FromRawPartsChecker,
+    MutAliasingChecker,
+    PtrCastChecker,
+    TransmuteChecker,
+    UninitExposureChecker,
     UnsafeDataflowChecker,
+    UnsafeImplChecker,
     //UnsafeDestructorChecker,
 };
 use crate::rudra::context::CtxOwner;
+use crate::rudra::dot;
+use crate::rudra::explain;
+use crate::rudra::inventory::{CrateInventory, InventoryFormat};
 use crate::rudra::log::Verbosity;
 use crate::rudra::report::ReportLevel;
 use charon_lib::ast::TranslatedCrate;
+use charon_lib::name_matcher::Pattern;
+use std::io::{self, Write};
 
 // Insert rustc arguments at the beginning of the argument list that Rudra wants to be
 // set per default, for maximal validation power.
@@ -18,9 +31,19 @@ pub static RUDRA_DEFAULT_ARGS: &[&str] =
 pub struct RudraConfig {
     pub verbosity: Verbosity,
     pub report_level: ReportLevel,
+    /// Only analyze the bodies which contain unsafe code
+    pub unsafe_only: bool,
     pub unsafe_destructor_enabled: bool,
     pub send_sync_variance_enabled: bool,
     pub unsafe_dataflow_enabled: bool,
+    pub uninit_exposure_enabled: bool,
+    pub double_free_enabled: bool,
+    pub mut_aliasing_enabled: bool,
+    pub transmute_enabled: bool,
+    pub from_raw_parts_enabled: bool,
+    pub ptr_cast_enabled: bool,
+    pub unsafe_impl_enabled: bool,
+    pub drop_panic_enabled: bool,
 }
 
 impl Default for RudraConfig {
@@ -29,9 +52,18 @@ impl Default for RudraConfig {
             verbosity: Verbosity::Normal,
             //verbosity: Verbosity::Trace,
             report_level: ReportLevel::Info,
+            unsafe_only: false,
             unsafe_destructor_enabled: false,
             send_sync_variance_enabled: true,
             unsafe_dataflow_enabled: true,
+            uninit_exposure_enabled: true,
+            double_free_enabled: true,
+            mut_aliasing_enabled: true,
+            transmute_enabled: true,
+            from_raw_parts_enabled: true,
+            ptr_cast_enabled: true,
+            unsafe_impl_enabled: true,
+            drop_panic_enabled: true,
         }
     }
 }
@@ -72,7 +104,7 @@ where
 
 pub fn analyze(crate_data: TranslatedCrate, config: RudraConfig) {
     // workaround to mimic arena lifetime
-    let rcx_owner = CtxOwner::new(crate_data, config.report_level);
+    let rcx_owner = CtxOwner::new(crate_data, config.report_level, config.unsafe_only);
     let rcx = &*Box::leak(Box::new(rcx_owner));
 
     // shadow the variable tcx
@@ -102,4 +134,135 @@ pub fn analyze(crate_data: TranslatedCrate, config: RudraConfig) {
             checker.analyze();
         })
     }
+
+    // Uninitialized memory exposure analysis
+    if config.uninit_exposure_enabled {
+        run_analysis("UninitExposure", || {
+            let checker = UninitExposureChecker::new(rcx);
+            checker.analyze();
+        })
+    }
+
+    // Double free analysis
+    if config.double_free_enabled {
+        run_analysis("DoubleFree", || {
+            let checker = DoubleFreeChecker::new(rcx);
+            checker.analyze();
+        })
+    }
+
+    // Mutable aliasing analysis
+    if config.mut_aliasing_enabled {
+        run_analysis("MutAliasing", || {
+            let checker = MutAliasingChecker::new(rcx);
+            checker.analyze();
+        })
+    }
+
+    // Transmute analysis
+    if config.transmute_enabled {
+        run_analysis("Transmute", || {
+            let checker = TransmuteChecker::new(rcx);
+            checker.analyze();
+        })
+    }
+
+    // Vec::from_raw_parts provenance analysis
+    if config.from_raw_parts_enabled {
+        run_analysis("FromRawParts", || {
+            let checker = FromRawPartsChecker::new(rcx);
+            checker.analyze();
+        })
+    }
+
+    // Pointer cast analysis
+    if config.ptr_cast_enabled {
+        run_analysis("PtrCast", || {
+            let checker = PtrCastChecker::new(rcx);
+            checker.analyze();
+        })
+    }
+
+    // Unsafe impl audit
+    if config.unsafe_impl_enabled {
+        run_analysis("UnsafeImpl", || {
+            let checker = UnsafeImplChecker::new(rcx);
+            checker.analyze();
+        })
+    }
+
+    // Destructor panic analysis
+    if config.drop_panic_enabled {
+        run_analysis("DropPanic", || {
+            let checker = DropPanicChecker::new(rcx);
+            checker.analyze();
+        })
+    }
+}
+
+/// Writes the unsafe inventory of the crate instead of running the analyses
+pub fn inventory(
+    crate_data: TranslatedCrate,
+    format: InventoryFormat,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let rcx_owner = CtxOwner::new(crate_data, ReportLevel::Info, false);
+    let rcx = &rcx_owner;
+
+    let inventory = run_analysis("Inventory", || CrateInventory::collect(rcx));
+    inventory.write(format, writer)
+}
+
+/// Writes the CFG of the local functions matching the name pattern `pattern`
+/// as Graphviz digraphs, instead of running the analyses. With `unsafe_only`,
+/// the functions without unsafe code are drawn without their taints.
+/// Returns the number of written functions.
+pub fn dot(
+    crate_data: TranslatedCrate,
+    pattern: &str,
+    unsafe_only: bool,
+    writer: &mut impl Write,
+) -> io::Result<usize> {
+    let pattern = Pattern::parse(pattern)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?}", e)))?;
+    let rcx_owner = CtxOwner::new(crate_data, ReportLevel::Info, unsafe_only);
+    let rcx = &rcx_owner;
+
+    let mut count = 0;
+    for decl in rcx.crate_data.fun_decls.iter() {
+        if decl.item_meta.is_local
+            && pattern.matches(&rcx.crate_data, &decl.item_meta.name)
+            && dot::write_cfg(rcx, decl, writer)?
+        {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Explains the analysis of the local functions matching the name pattern
+/// `pattern`, instead of running the analyses. With `unsafe_only`, the
+/// functions without unsafe code are reported as not analyzed.
+/// Returns the number of explained functions.
+pub fn explain(
+    crate_data: TranslatedCrate,
+    pattern: &str,
+    unsafe_only: bool,
+    writer: &mut impl Write,
+) -> io::Result<usize> {
+    let pattern = Pattern::parse(pattern)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?}", e)))?;
+    let rcx_owner = CtxOwner::new(crate_data, ReportLevel::Info, unsafe_only);
+    let rcx = &rcx_owner;
+
+    let mut count = 0;
+    for decl in rcx.crate_data.fun_decls.iter() {
+        if decl.item_meta.is_local
+            && pattern.matches(&rcx.crate_data, &decl.item_meta.name)
+            && explain::write_explanation(rcx, decl, writer)?
+        {
+            count += 1;
+        }
+    }
+    Ok(count)
 }
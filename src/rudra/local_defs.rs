@@ -0,0 +1,259 @@
+//! Definitions of the locals of a ULLBC body.
+//! This is used to find out where the value of an operand comes from, e.g.
+//! which local a raw pointer has been derived from.
+
+use std::collections::{HashMap, HashSet};
+
+use charon_lib::gast::FunDeclId;
+use charon_lib::ullbc_ast::{
+    AggregateKind, BodyContents, Call, FnOperand, Operand, Place, ProjectionElem, RawStatement,
+    Rvalue, UnOp, VarId,
+};
+
+/// A statement that writes a whole local.
+#[derive(Clone, Copy)]
+pub enum LocalDef<'a> {
+    Assign(&'a Rvalue),
+    Call(&'a Call),
+}
+
+impl<'a> LocalDef<'a> {
+    /// The locals the defined value is computed from.
+    pub fn used_locals(&self) -> Vec<VarId> {
+        let places = match self {
+            LocalDef::Assign(rvalue) => rvalue_places(rvalue),
+            LocalDef::Call(call) => call_places(call),
+        };
+        places.into_iter().map(|place| place.var_id).collect()
+    }
+}
+
+pub struct LocalDefs<'a> {
+    defs: HashMap<VarId, Vec<LocalDef<'a>>>,
+}
+
+impl<'a> LocalDefs<'a> {
+    pub fn new(body: &'a BodyContents) -> Self {
+        let mut defs: HashMap<VarId, Vec<LocalDef<'a>>> = HashMap::new();
+        for block in body.iter() {
+            for st in &block.statements {
+                let (place, def) = match &st.content {
+                    RawStatement::Assign(place, rvalue) => (place, LocalDef::Assign(rvalue)),
+                    RawStatement::Call(call) => (&call.dest, LocalDef::Call(call)),
+                    _ => continue,
+                };
+                // Writes to a part of a local don't define its value
+                if place.projection.is_empty() {
+                    defs.entry(place.var_id).or_default().push(def);
+                }
+            }
+        }
+        LocalDefs { defs }
+    }
+
+    pub fn defs(&self, var_id: VarId) -> &[LocalDef<'a>] {
+        self.defs.get(&var_id).map_or(&[], |defs| &defs[..])
+    }
+
+    /// Returns the definition of a local if it is assigned exactly once.
+    pub fn unique_def(&self, var_id: VarId) -> Option<LocalDef<'a>> {
+        match self.defs(var_id) {
+            [def] => Some(*def),
+            _ => None,
+        }
+    }
+
+    /// Follows the copies and moves of `op` back to the definition of its value.
+    /// A field read from the result of a checked arithmetic operation
+    /// (`x = move (tmp.0)` with `tmp = CheckedSub(a, b)`) resolves to the operation.
+    pub fn value_def(&self, op: &'a Operand) -> Option<LocalDef<'a>> {
+        let mut place = operand_place(op)?;
+        let mut visited = HashSet::new();
+        loop {
+            if !visited.insert(place.var_id) {
+                return None;
+            }
+            let def = self.unique_def(place.var_id)?;
+            match (&place.projection[..], def) {
+                ([], LocalDef::Assign(Rvalue::Use(Operand::Copy(next) | Operand::Move(next)))) => {
+                    place = next
+                }
+                ([], def) => return Some(def),
+                ([ProjectionElem::Field(..)], LocalDef::Assign(Rvalue::BinaryOp(..))) => {
+                    return Some(def)
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Returns the local that `place` has been derived from, following copies,
+    /// casts, references and raw pointers: for `p = &raw mut (*x).f; q = p as *const u8`,
+    /// the root of `q` is `x`.
+    pub fn place_root(&self, place: &Place) -> VarId {
+        let mut var_id = place.var_id;
+        let mut visited = HashSet::new();
+        while visited.insert(var_id) {
+            match self.unique_def(var_id) {
+                Some(LocalDef::Assign(
+                    Rvalue::Use(Operand::Copy(next) | Operand::Move(next))
+                    | Rvalue::Ref(next, _)
+                    | Rvalue::RawPtr(next, _)
+                    | Rvalue::UnaryOp(UnOp::Cast(_), Operand::Copy(next) | Operand::Move(next)),
+                )) => var_id = next.var_id,
+                _ => break,
+            }
+        }
+        var_id
+    }
+
+    /// Same as `place_root`, but stops at the values loaded through a
+    /// projection: for `p = copy ((*self).ptr)`, the root of `p` is `p`, as
+    /// the pointer stored in the field doesn't point to `self`. The references
+    /// and raw pointers to a projection (`&raw (*x).f`) are still followed.
+    pub fn address_root(&self, place: &Place) -> VarId {
+        let mut var_id = place.var_id;
+        let mut visited = HashSet::new();
+        while visited.insert(var_id) {
+            match self.unique_def(var_id) {
+                Some(LocalDef::Assign(
+                    Rvalue::Use(Operand::Copy(next) | Operand::Move(next))
+                    | Rvalue::UnaryOp(UnOp::Cast(_), Operand::Copy(next) | Operand::Move(next)),
+                )) if next.projection.is_empty() => var_id = next.var_id,
+                Some(LocalDef::Assign(Rvalue::Ref(next, _) | Rvalue::RawPtr(next, _))) => {
+                    var_id = next.var_id
+                }
+                _ => break,
+            }
+        }
+        var_id
+    }
+
+    /// Returns all the locals `var_id` may be computed from, including
+    /// through function calls: unlike `place_root`, this doesn't stop at
+    /// `p = UnsafeCell::get(move q)`. The result contains `var_id`.
+    pub fn origins(&self, var_id: VarId) -> HashSet<VarId> {
+        let mut origins = HashSet::from([var_id]);
+        let mut work_list = vec![var_id];
+        while let Some(var_id) = work_list.pop() {
+            for def in self.defs(var_id) {
+                for used in def.used_locals() {
+                    if origins.insert(used) {
+                        work_list.push(used);
+                    }
+                }
+            }
+        }
+        origins
+    }
+
+    /// Returns all the locals whose value may be computed from `var_id`.
+    /// The result contains `var_id`.
+    pub fn derived(&self, var_id: VarId) -> HashSet<VarId> {
+        let mut derived = HashSet::from([var_id]);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (defined, defs) in self.defs.iter() {
+                if !derived.contains(defined)
+                    && defs
+                        .iter()
+                        .any(|def| def.used_locals().iter().any(|used| derived.contains(used)))
+                {
+                    derived.insert(*defined);
+                    changed = true;
+                }
+            }
+        }
+        derived
+    }
+
+    /// Returns the closures of the crate that `op` may hold, i.e. the closures
+    /// built by the definitions of its origins.
+    pub fn closures(&self, op: &Operand) -> Vec<FunDeclId> {
+        let root = match operand_place(op) {
+            Some(place) => place.var_id,
+            None => return Vec::new(),
+        };
+        let mut closures: Vec<FunDeclId> = self
+            .origins(root)
+            .into_iter()
+            .flat_map(|var_id| self.defs(var_id))
+            .filter_map(|def| match def {
+                LocalDef::Assign(Rvalue::Aggregate(AggregateKind::Closure(closure_id, _), _)) => {
+                    Some(*closure_id)
+                }
+                _ => None,
+            })
+            .collect();
+        closures.sort();
+        closures.dedup();
+        closures
+    }
+
+    /// Same as `place_root` for an operand. Constants don't have a root.
+    pub fn operand_root(&self, op: &Operand) -> Option<VarId> {
+        operand_place(op).map(|place| self.place_root(place))
+    }
+
+    pub fn operand_address_root(&self, op: &Operand) -> Option<VarId> {
+        operand_place(op).map(|place| self.address_root(place))
+    }
+}
+
+pub fn operand_place(op: &Operand) -> Option<&Place> {
+    match op {
+        Operand::Copy(place) | Operand::Move(place) => Some(place),
+        Operand::Const(_) => None,
+    }
+}
+
+/// Returns true if the statement moves (a part of) the local out.
+pub fn statement_moves(st: &RawStatement, var_id: VarId) -> bool {
+    let is_moved = |op: &Operand| matches!(op, Operand::Move(place) if place.var_id == var_id);
+    match st {
+        RawStatement::Assign(_, rvalue) => match rvalue {
+            Rvalue::Use(op) | Rvalue::UnaryOp(_, op) => is_moved(op),
+            Rvalue::BinaryOp(_, lhs, rhs) => is_moved(lhs) || is_moved(rhs),
+            Rvalue::Aggregate(_, ops) => ops.iter().any(is_moved),
+            _ => false,
+        },
+        RawStatement::Call(call) => {
+            call.args.iter().any(is_moved)
+                || matches!(&call.func, FnOperand::Move(place) if place.var_id == var_id)
+        }
+        _ => false,
+    }
+}
+
+fn rvalue_places(rvalue: &Rvalue) -> Vec<&Place> {
+    match rvalue {
+        Rvalue::Use(op) | Rvalue::UnaryOp(_, op) => operand_place(op).into_iter().collect(),
+        Rvalue::BinaryOp(_, lhs, rhs) => [lhs, rhs].into_iter().filter_map(operand_place).collect(),
+        Rvalue::Aggregate(_, ops) => ops.iter().filter_map(operand_place).collect(),
+        Rvalue::Ref(place, _) | Rvalue::RawPtr(place, _) => vec![place],
+        _ => vec![],
+    }
+}
+
+fn call_places(call: &Call) -> Vec<&Place> {
+    let mut places: Vec<&Place> = call.args.iter().filter_map(operand_place).collect();
+    if let FnOperand::Move(place) = &call.func {
+        places.push(place);
+    }
+    places
+}
+
+/// Returns the places read by the statement, including the locals written
+/// through (`*p = x` reads `p`). Drops are not considered as reads.
+pub fn statement_places(st: &RawStatement) -> Vec<&Place> {
+    let (dest, mut places) = match st {
+        RawStatement::Assign(dest, rvalue) => (dest, rvalue_places(rvalue)),
+        RawStatement::Call(call) => (&call.dest, call_places(call)),
+        _ => return vec![],
+    };
+    if !dest.projection.is_empty() {
+        places.push(dest);
+    }
+    places
+}
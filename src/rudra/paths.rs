@@ -5,7 +5,9 @@ use maplit::hashmap;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 
-use crate::rudra::analysis::UnsafeDataflowBehaviorFlag;
+use crate::rudra::analysis::{
+    UninitExposureBehaviorFlag, UnsafeDataflowBehaviorFlag, UnsafeImplBehaviorFlag,
+};
 
 /*
 How to find a path for unknown item:
@@ -60,6 +62,59 @@ pub const PTR_SLICE_FROM_RAW_PARTS_MUT: [&str; 3] = ["core", "ptr", "slice_from_
 pub const SLICE_FROM_RAW_PARTS: [&str; 4] = ["core", "slice", "raw", "from_raw_parts"];
 pub const SLICE_FROM_RAW_PARTS_MUT: [&str; 4] = ["core", "slice", "raw", "from_raw_parts_mut"];
 
+// Uninitialized memory
+pub const VEC_WITH_CAPACITY: [&str; 4] = ["alloc", "vec", "_", "with_capacity"];
+pub const MAYBE_UNINIT_UNINIT: [&str; 5] = ["core", "mem", "maybe_uninit", "_", "uninit"];
+pub const MAYBE_UNINIT_ASSUME_INIT: [&str; 5] = ["core", "mem", "maybe_uninit", "_", "assume_init"];
+pub const MAYBE_UNINIT_WRITE: [&str; 5] = ["core", "mem", "maybe_uninit", "_", "write"];
+pub const MEM_UNINITIALIZED: [&str; 3] = ["core", "mem", "uninitialized"];
+pub const MEM_ZEROED: [&str; 3] = ["core", "mem", "zeroed"];
+pub const PTR_WRITE_BYTES: [&str; 3] = ["core", "intrinsics", "write_bytes"];
+
+pub const NON_NULL: [&str; 4] = ["core", "ptr", "non_null", "NonNull"];
+
+// Leak-safe operations
+pub const MEM_FORGET: [&str; 3] = ["core", "mem", "forget"];
+pub const MANUALLY_DROP_NEW: [&str; 5] = ["core", "mem", "manually_drop", "_", "new"];
+pub const VEC_LEN: [&str; 4] = ["alloc", "vec", "_", "len"];
+
+// Allocation provenance
+pub const VEC: [&str; 3] = ["alloc", "vec", "Vec"];
+pub const VEC_AS_PTR: [&str; 4] = ["alloc", "vec", "_", "as_ptr"];
+pub const VEC_AS_MUT_PTR: [&str; 4] = ["alloc", "vec", "_", "as_mut_ptr"];
+pub const BOX_INTO_RAW: [&str; 4] = ["alloc", "boxed", "_", "into_raw"];
+pub const SLICE_AS_PTR: [&str; 4] = ["core", "slice", "_", "as_ptr"];
+pub const SLICE_AS_MUT_PTR: [&str; 4] = ["core", "slice", "_", "as_mut_ptr"];
+pub const STR_AS_PTR: [&str; 4] = ["core", "str", "_", "as_ptr"];
+
+// Traits with safety invariants
+pub const SEND: [&str; 3] = ["core", "marker", "Send"];
+pub const SYNC: [&str; 3] = ["core", "marker", "Sync"];
+pub const UNPIN: [&str; 3] = ["core", "marker", "Unpin"];
+pub const GLOBAL_ALLOC: [&str; 4] = ["core", "alloc", "global", "GlobalAlloc"];
+pub const ALLOCATOR: [&str; 3] = ["core", "alloc", "Allocator"];
+pub const TRUSTED_LEN: [&str; 5] = ["core", "iter", "traits", "marker", "TrustedLen"];
+pub const SEARCHER: [&str; 4] = ["core", "str", "pattern", "Searcher"];
+
+// Destructors
+pub const DROP: [&str; 4] = ["core", "ops", "drop", "Drop"];
+pub const MANUALLY_DROP_TAKE: [&str; 5] = ["core", "mem", "manually_drop", "_", "take"];
+
+// Closures
+pub const FN_ONCE: [&str; 4] = ["core", "ops", "function", "FnOnce"];
+pub const FN_MUT: [&str; 4] = ["core", "ops", "function", "FnMut"];
+pub const FN: [&str; 4] = ["core", "ops", "function", "Fn"];
+
+// Panics
+pub const PANIC: [&str; 3] = ["core", "panicking", "panic"];
+pub const PANIC_FMT: [&str; 3] = ["core", "panicking", "panic_fmt"];
+pub const ASSERT_FAILED: [&str; 3] = ["core", "panicking", "assert_failed"];
+pub const BEGIN_PANIC: [&str; 3] = ["std", "panicking", "begin_panic"];
+pub const OPTION_UNWRAP: [&str; 4] = ["core", "option", "_", "unwrap"];
+pub const OPTION_EXPECT: [&str; 4] = ["core", "option", "_", "expect"];
+pub const RESULT_UNWRAP: [&str; 4] = ["core", "result", "_", "unwrap"];
+pub const RESULT_EXPECT: [&str; 4] = ["core", "result", "_", "expect"];
+
 // Generic function call
 pub const PTR_DROP_IN_PLACE: [&str; 3] = ["core", "ptr", "drop_in_place"];
 pub const PTR_DIRECT_DROP_IN_PLACE: [&str; 5] = ["core", "ptr", "mut_ptr", "_", "drop_in_place"];
@@ -135,6 +190,136 @@ pub static WEAK_LIFETIME_BYPASS_LIST: Lazy<PathSet> = Lazy::new(move || {
     ])
 });
 
+pub static NON_NULL_LIST: Lazy<PathSet> = Lazy::new(move || PathSet::new(&[&NON_NULL]));
+
+/// Functions returning uninitialized memory
+pub static UNINIT_SOURCE_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&VEC_WITH_CAPACITY, &MAYBE_UNINIT_UNINIT]));
+
+/// Functions exposing the memory returned by `UNINIT_SOURCE_LIST` as initialized
+pub static UNINIT_SINK_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&VEC_SET_LEN, &MAYBE_UNINIT_ASSUME_INIT]));
+
+/// Functions creating uninitialized values out of thin air
+pub static UNINIT_VALUE_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&MEM_UNINITIALIZED, &MEM_ZEROED]));
+
+/// Functions initializing memory through a raw pointer
+pub static INIT_WRITE_LIST: Lazy<PathSet> = Lazy::new(move || {
+    PathSet::new(&[
+        &PTR_WRITE,
+        &PTR_DIRECT_WRITE,
+        &INTRINSICS_COPY,
+        &INTRINSICS_COPY_NONOVERLAPPING,
+        &PTR_WRITE_BYTES,
+        &MAYBE_UNINIT_WRITE,
+    ])
+});
+
+/// For each function of `INIT_WRITE_LIST`, the index of the argument pointing
+/// to the written memory
+pub static INIT_WRITE_DEST_MAP: Lazy<HashMap<String, usize>> = Lazy::new(move || {
+    hashmap! {
+        slice_to_string(&PTR_WRITE)=> 0,
+        slice_to_string(&PTR_DIRECT_WRITE)=> 0,
+        //
+        slice_to_string(&INTRINSICS_COPY)=> 1,
+        slice_to_string(&INTRINSICS_COPY_NONOVERLAPPING)=> 1,
+        //
+        slice_to_string(&PTR_WRITE_BYTES)=> 0,
+        slice_to_string(&MAYBE_UNINIT_WRITE)=> 0,
+    }
+});
+
+pub static TRANSMUTE_LIST: Lazy<PathSet> = Lazy::new(move || PathSet::new(&[&TRANSMUTE]));
+
+/// Functions creating a `&mut` from a raw pointer
+pub static PTR_AS_MUT_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&PTR_AS_MUT, &NON_NULL_AS_MUT]));
+
+/// Functions accessing the value behind a raw pointer
+pub static PTR_ACCESS_LIST: Lazy<PathSet> = Lazy::new(move || {
+    PathSet::new(&[
+        &PTR_READ,
+        &PTR_DIRECT_READ,
+        &PTR_WRITE,
+        &PTR_DIRECT_WRITE,
+        &PTR_AS_REF,
+        &PTR_AS_MUT,
+    ])
+});
+
+/// Functions duplicating the value behind a raw pointer
+pub static PTR_READ_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&PTR_READ, &PTR_DIRECT_READ]));
+
+/// Functions overwriting the value behind a raw pointer without dropping it
+pub static PTR_WRITE_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&PTR_WRITE, &PTR_DIRECT_WRITE]));
+
+pub static VEC_FROM_RAW_PARTS_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&VEC_FROM_RAW_PARTS]));
+
+pub static VEC_LIST: Lazy<PathSet> = Lazy::new(move || PathSet::new(&[&VEC]));
+
+/// Functions returning a pointer to a `Vec` or `Box` allocation
+pub static HEAP_PTR_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&VEC_AS_PTR, &VEC_AS_MUT_PTR, &BOX_INTO_RAW]));
+
+/// Functions returning a pointer to a buffer which may not be heap allocated
+pub static BUFFER_PTR_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&SLICE_AS_PTR, &SLICE_AS_MUT_PTR, &STR_AS_PTR]));
+
+pub static VEC_LEN_LIST: Lazy<PathSet> = Lazy::new(move || PathSet::new(&[&VEC_LEN]));
+
+pub static VEC_WITH_CAPACITY_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&VEC_WITH_CAPACITY]));
+
+/// Traits whose impls are audited even if they are not declared `unsafe` in the crate
+pub static AUDITED_TRAIT_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&SEND, &SYNC, &UNPIN, &GLOBAL_ALLOC]));
+
+/// The `unsafe trait`s of the standard library
+pub static UNSAFE_TRAIT_LIST: Lazy<PathSet> = Lazy::new(move || {
+    PathSet::new(&[
+        &SEND,
+        &SYNC,
+        &GLOBAL_ALLOC,
+        &ALLOCATOR,
+        &TRUSTED_LEN,
+        &SEARCHER,
+    ])
+});
+
+pub static DROP_TRAIT_LIST: Lazy<PathSet> = Lazy::new(move || PathSet::new(&[&DROP]));
+
+/// The traits implemented by closures
+pub static FN_TRAIT_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&FN_ONCE, &FN_MUT, &FN]));
+
+/// Functions moving a field out of a value being dropped
+pub static TEARDOWN_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&PTR_READ, &PTR_DIRECT_READ, &MANUALLY_DROP_TAKE]));
+
+/// Functions which panic, or may panic on some values
+pub static PANIC_FN_LIST: Lazy<PathSet> = Lazy::new(move || {
+    PathSet::new(&[
+        &PANIC,
+        &PANIC_FMT,
+        &ASSERT_FAILED,
+        &BEGIN_PANIC,
+        //
+        &OPTION_UNWRAP,
+        &OPTION_EXPECT,
+        &RESULT_UNWRAP,
+        &RESULT_EXPECT,
+    ])
+});
+
+/// Operations that give up the ownership of a value without dropping it
+pub static LEAK_SAFE_LIST: Lazy<PathSet> =
+    Lazy::new(move || PathSet::new(&[&MEM_FORGET, &MANUALLY_DROP_NEW]));
+
 pub static GENERIC_FN_LIST: Lazy<PathSet> =
     Lazy::new(move || PathSet::new(&[&PTR_DROP_IN_PLACE, &PTR_DIRECT_DROP_IN_PLACE]));
 
@@ -179,3 +364,70 @@ pub static WEAK_BYPASS_MAP: Lazy<PathMap> = Lazy::new(move || {
         slice_to_string(&SLICE_FROM_RAW_PARTS_MUT)=> BehaviorFlag::SLICE_FROM_RAW,
     }
 });
+
+/// For each lifetime bypass, the index of the generic type argument that holds
+/// the type of the bypassed values. A bypass on a Copy element type is harmless:
+/// the duplicated or uninitialized values don't have drop glue.
+/// `transmute` changes the type of the value and has no such argument.
+/// `Vec::set_len` is not listed: it exposes uninitialized memory whatever the
+/// element type is.
+pub static BYPASS_ELEMENT_TY_MAP: Lazy<HashMap<String, usize>> = Lazy::new(move || {
+    hashmap! {
+        slice_to_string(&PTR_READ)=> 0,
+        slice_to_string(&PTR_DIRECT_READ)=> 0,
+        //
+        slice_to_string(&INTRINSICS_COPY)=> 0,
+        slice_to_string(&INTRINSICS_COPY_NONOVERLAPPING)=> 0,
+        //
+        slice_to_string(&VEC_FROM_RAW_PARTS)=> 0,
+        //
+        slice_to_string(&PTR_WRITE)=> 0,
+        slice_to_string(&PTR_DIRECT_WRITE)=> 0,
+        //
+        slice_to_string(&PTR_AS_REF)=> 0,
+        slice_to_string(&PTR_AS_MUT)=> 0,
+        slice_to_string(&NON_NULL_AS_REF)=> 0,
+        slice_to_string(&NON_NULL_AS_MUT)=> 0,
+        //
+        slice_to_string(&SLICE_GET_UNCHECKED)=> 0,
+        slice_to_string(&SLICE_GET_UNCHECKED_MUT)=> 0,
+        //
+        slice_to_string(&PTR_SLICE_FROM_RAW_PARTS)=> 0,
+        slice_to_string(&PTR_SLICE_FROM_RAW_PARTS_MUT)=> 0,
+        slice_to_string(&SLICE_FROM_RAW_PARTS)=> 0,
+        slice_to_string(&SLICE_FROM_RAW_PARTS_MUT)=> 0,
+    }
+});
+
+type UninitPathMap = HashMap<String, UninitExposureBehaviorFlag>;
+
+/// The kind of uninitialized memory created by `UNINIT_SOURCE_LIST`, exposed by
+/// `UNINIT_SINK_LIST` or `UNINIT_VALUE_LIST`
+pub static UNINIT_MAP: Lazy<UninitPathMap> = Lazy::new(move || {
+    use UninitExposureBehaviorFlag as BehaviorFlag;
+
+    hashmap! {
+        slice_to_string(&VEC_WITH_CAPACITY)=> BehaviorFlag::VEC_SET_LEN,
+        slice_to_string(&VEC_SET_LEN)=> BehaviorFlag::VEC_SET_LEN,
+        //
+        slice_to_string(&MAYBE_UNINIT_UNINIT)=> BehaviorFlag::ASSUME_INIT,
+        slice_to_string(&MAYBE_UNINIT_ASSUME_INIT)=> BehaviorFlag::ASSUME_INIT,
+        //
+        slice_to_string(&MEM_UNINITIALIZED)=> BehaviorFlag::MEM_UNINITIALIZED,
+        slice_to_string(&MEM_ZEROED)=> BehaviorFlag::MEM_ZEROED,
+    }
+});
+
+type UnsafeImplPathMap = HashMap<String, UnsafeImplBehaviorFlag>;
+
+/// The kind of impl of the traits in `AUDITED_TRAIT_LIST`
+pub static UNSAFE_IMPL_MAP: Lazy<UnsafeImplPathMap> = Lazy::new(move || {
+    use UnsafeImplBehaviorFlag as BehaviorFlag;
+
+    hashmap! {
+        slice_to_string(&SEND)=> BehaviorFlag::SEND_IMPL,
+        slice_to_string(&SYNC)=> BehaviorFlag::SYNC_IMPL,
+        slice_to_string(&UNPIN)=> BehaviorFlag::UNPIN_IMPL,
+        slice_to_string(&GLOBAL_ALLOC)=> BehaviorFlag::GLOBAL_ALLOC_IMPL,
+    }
+});
@@ -5,13 +5,14 @@ use std::borrow::Cow;
 use std::env;
 use std::fmt;
 use std::fs;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use serde::Serialize;
 
+use crate::rudra::call_graph::ApiExposure;
 use crate::rudra::utils;
 
 use charon_lib::ast::TranslatedCrate;
@@ -41,10 +42,31 @@ pub fn init_report_logger(report_logger: Box<dyn ReportLogger>) -> FlushHandle {
     FlushHandle { _priv: () }
 }
 
-pub fn default_report_logger() -> Box<dyn ReportLogger> {
+/// When to color the source snippets of the reports
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorMode {
+    /// Color the reports printed to a terminal, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+pub fn default_report_logger(color: ColorMode) -> Box<dyn ReportLogger> {
     match env::var_os("RUDRA_REPORT_PATH") {
-        Some(val) => Box::new(FileLogger::new(val)),
-        None => Box::new(StderrLogger::new()),
+        // Report files are read in editors and ticket trackers, which do not
+        // render ANSI escapes
+        Some(val) => Box::new(FileLogger::new(val, color == ColorMode::Always)),
+        None => {
+            let color = match color {
+                ColorMode::Auto => {
+                    env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+                }
+                ColorMode::Always => true,
+                ColorMode::Never => false,
+            };
+            Box::new(StderrLogger::new(color))
+        }
     }
 }
 
@@ -74,6 +96,8 @@ pub struct Report {
     analyzer: Cow<'static, str>,
     description: Cow<'static, str>,
     location: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exposure: Option<ApiExposure>,
     source: String,
 }
 
@@ -124,30 +148,45 @@ impl Report {
         U: Into<Cow<'static, str>>,
     {
         let location = color_span.to_string();
+        let color = REPORT_LOGGER
+            .get()
+            .map_or(false, |logger| logger.use_color());
 
         Report {
             level,
             analyzer: analyzer.into(),
             description: description.into(),
             location,
-            source: color_span.to_colored_string(),
+            exposure: None,
+            source: color_span.to_annotated_string(color),
         }
     }
+
+    /// Attaches the exposure to the public API of the reported function
+    pub fn with_exposure(mut self, exposure: ApiExposure) -> Report {
+        self.exposure = Some(exposure);
+        self
+    }
 }
 
 pub trait ReportLogger: Sync + Send {
     fn log(&self, report: Report);
     fn flush(&self);
+    /// Whether the source snippets are colored with ANSI escapes. Otherwise,
+    /// the labels are only marked with underlines.
+    fn use_color(&self) -> bool;
 }
 
 struct StderrLogger {
     reports: Mutex<Vec<Report>>,
+    color: bool,
 }
 
 impl StderrLogger {
-    fn new() -> Self {
+    fn new(color: bool) -> Self {
         StderrLogger {
             reports: Mutex::new(Vec::new()),
+            color,
         }
     }
 }
@@ -157,19 +196,27 @@ impl ReportLogger for StderrLogger {
         self.reports.lock().push(report);
     }
 
+    fn use_color(&self) -> bool {
+        self.color
+    }
+
     fn flush(&self) {
         let stderr = std::io::stderr();
         let mut handle = stderr.lock();
 
         let reports = self.reports.lock();
         for report in reports.iter() {
+            let exposure = report
+                .exposure
+                .map_or(String::new(), |exposure| format!(" ({})", exposure));
             writeln!(
                 &mut handle,
-                "{} ({}): {}\n-> {}\n{}",
+                "{} ({}): {}\n-> {}{}\n{}",
                 &report.level,
                 &report.analyzer,
                 &report.description,
                 &report.location,
+                exposure,
                 &report.source
             )
             .expect("stderr closed");
@@ -180,16 +227,18 @@ impl ReportLogger for StderrLogger {
 struct FileLogger {
     reports: Mutex<Vec<Report>>,
     file_path: PathBuf,
+    color: bool,
 }
 
 impl FileLogger {
-    fn new<T>(val: T) -> Self
+    fn new<T>(val: T, color: bool) -> Self
     where
         T: Into<PathBuf>,
     {
         FileLogger {
             reports: Mutex::new(Vec::new()),
             file_path: val.into(),
+            color,
         }
     }
 }
@@ -199,6 +248,10 @@ impl ReportLogger for FileLogger {
         self.reports.lock().push(report);
     }
 
+    fn use_color(&self) -> bool {
+        self.color
+    }
+
     fn flush(&self) {
         #[derive(Serialize)]
         struct Reports<'a> {
@@ -208,18 +261,26 @@ impl ReportLogger for FileLogger {
         let reports = self.reports.lock();
         if !reports.is_empty() {
             let reports_ref = &*reports;
-            fs::write(
-                &self.file_path,
+            let content = if self
+                .file_path
+                .extension()
+                .map_or(false, |ext| ext == "json")
+            {
+                serde_json::to_string_pretty(&Reports {
+                    reports: reports_ref,
+                })
+                .expect("failed to serialize Rudra report")
+            } else {
                 toml::to_string_pretty(&Reports {
                     reports: reports_ref,
                 })
                 .expect("failed to serialize Rudra report")
                 // We manually converts some characters inside toml strings
-                // Match this list with test.py
+                // so that the colored source stays readable
                 .replace("\\u001B", "\u{001B}")
-                .replace("\\t", "\t"),
-            )
-            .expect("cannot write Rudra report to file");
+                .replace("\\t", "\t")
+            };
+            fs::write(&self.file_path, content).expect("cannot write Rudra report to file");
         }
     }
 }
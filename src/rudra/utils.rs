@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::io::Write;
 use std::rc::Rc;
 
@@ -5,97 +6,38 @@ use std::rc::Rc;
 //use rustc_middle::ty::{Instance, InstanceDef, TyCtxt};
 //use rustc_span::{CharPos, Span};
 
-use charon_lib::ast::meta::{FileName, Loc, Span};
+use charon_lib::ast::meta::{FileId, FileName, Loc, Span};
 use charon_lib::ast::TranslatedCrate;
+use charon_lib::formatter::FmtCtx;
+use charon_lib::pretty::FmtWithCtx;
+use charon_lib::ullbc_ast::BlockData;
 use termcolor::{Buffer, Color, ColorSpec, WriteColor};
 use tracing::{error, info, warn};
 
 use crate::rudra::lib::compile_time_sysroot;
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-struct ColorEventId(usize);
-
-struct ColorStack(Vec<(Color, ColorEventId)>);
-
-impl ColorStack {
-    pub fn new() -> Self {
-        ColorStack(Vec::new())
-    }
-
-    pub fn handle_event(&mut self, event: &ColorEvent) {
-        match event.color {
-            Some(color) => self.0.push((color, event.id)),
-            None => {
-                for i in (0..self.0.len()).rev() {
-                    if self.0[i].1 == event.id {
-                        self.0.remove(i);
-                        return;
-                    }
-                }
-            }
-        };
-    }
-
-    pub fn current_spec(&self) -> ColorSpec {
-        let mut spec = ColorSpec::new();
-
-        match self.0.last() {
-            Some((color, _)) => spec.set_fg(Some(*color)),
-            None => spec.set_reset(true),
-        };
-
-        spec
-    }
+/// Whether a label marks the cause of a report, or its context
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelKind {
+    Primary,
+    Secondary,
 }
 
 #[derive(Clone)]
-struct ColorEvent {
-    // Some(color) for start, None for clear
-    color: Option<Color>,
-    line: usize,
-    col: usize,
-    id: ColorEventId,
+struct SpanLabel {
+    kind: LabelKind,
+    color: Color,
+    span: Span,
+    message: String,
 }
 
+/// The number of source lines shown around each label
+const CONTEXT_LINES: usize = 1;
+
 pub struct ColorSpan<'tcx> {
     crate_data: &'tcx TranslatedCrate,
     pub main_span: Span,
-    id_counter: usize,
-    sub_span_events: Vec<ColorEvent>,
-}
-
-impl PartialEq for ColorEvent {
-    fn eq(&self, other: &Self) -> bool {
-        self.line == other.line
-            && self.col == other.col
-            && self.color.is_some() == other.color.is_some()
-    }
-}
-
-impl Eq for ColorEvent {}
-
-impl PartialOrd for ColorEvent {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Ord for ColorEvent {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.line != other.line {
-            return self.line.cmp(&other.line);
-        }
-
-        if self.col != other.col {
-            return self.col.cmp(&other.col);
-        }
-
-        if self.color.is_some() != other.color.is_some() {
-            return self.color.is_some().cmp(&other.color.is_some());
-        }
-
-        std::cmp::Ordering::Equal
-    }
+    labels: Vec<SpanLabel>,
 }
 
 impl<'tcx> ColorSpan<'tcx> {
@@ -103,8 +45,7 @@ impl<'tcx> ColorSpan<'tcx> {
         Some(ColorSpan {
             crate_data,
             main_span,
-            id_counter: 0,
-            sub_span_events: Vec::new(),
+            labels: Vec::new(),
         })
     }
 
@@ -112,95 +53,155 @@ impl<'tcx> ColorSpan<'tcx> {
         self.main_span
     }
 
-    /// Returns true if span is successfully added
-    pub fn add_sub_span(&mut self, color: Color, span: Span) -> bool {
-        // Reports from macros may be in another file and we don't handle them
-        if span.span.file_id != self.main_span.span.file_id {
-            return false;
-        }
-
-        let event_id = ColorEventId(self.id_counter);
-        self.id_counter += 1;
-
-        self.sub_span_events.push(ColorEvent {
-            color: Some(color),
-            line: span.span.beg.line,
-            col: span.span.beg.col,
-            id: event_id,
+    /// Adds a labeled span, shown under the source line by
+    /// `to_annotated_string`. The labels in another file than the main span
+    /// are shown in a separate section with their own location.
+    pub fn add_label(
+        &mut self,
+        kind: LabelKind,
+        color: Color,
+        span: Span,
+        message: impl Into<String>,
+    ) {
+        self.labels.push(SpanLabel {
+            kind,
+            color,
+            span,
+            message: message.into(),
         });
-        self.sub_span_events.push(ColorEvent {
-            color: None,
-            line: span.span.end.line,
-            col: span.span.end.col,
-            id: event_id,
-        });
-        return true;
     }
 
-    pub fn to_colored_string(&self) -> String {
-        let mut events = self.sub_span_events.clone();
-        events.sort();
-        let mut events_iter = events.into_iter().peekable();
-
-        let mut buffer = Buffer::ansi();
-
-        if let Ok(snippet) = span_to_snippet(&self.crate_data, &self.main_span) {
-            let start_loc = self.main_span.span.beg;
-            let end_loc = self.main_span.span.end;
+    /// Renders the labeled lines in the style of rustc diagnostics: the first
+    /// line of the main span, the lines of the labels with some context, and
+    /// line numbers in the gutter. The other lines are elided. Without
+    /// `color`, the labels are only marked by their underlines.
+    pub fn to_annotated_string(&self, color: bool) -> String {
+        let main_file = self.main_span.span.file_id;
+        let main_content = match self.crate_data.file_id_to_content.get(&main_file) {
+            Some(content) => content,
+            None => return format!("Unable to get span for {:?}", self.main_span),
+        };
 
-            while let Some(event) = events_iter.peek() {
-                if event.line < start_loc.line {
-                    // Discard spans before the start loc
-                    events_iter.next();
-                } else {
-                    break;
-                }
-            }
+        let mut labels = self.labels.clone();
+        labels.sort_by_key(|label| {
+            (
+                label.span.span.file_id != main_file,
+                label.span.span.file_id,
+                label.span.span.beg.line,
+                label.span.span.beg.col,
+            )
+        });
 
-            let mut color_stack = ColorStack::new();
-            for (line_idx, line_content) in (start_loc.line..=end_loc.line).zip(snippet.into_iter())
+        // One section per file, starting with the file of the main span
+        let mut sections = vec![(
+            self.main_span,
+            main_content.as_str(),
+            shown_lines(
+                main_content,
+                &labels,
+                main_file,
+                Some(self.main_span.span.beg.line),
+            ),
+        )];
+        for label in labels.iter() {
+            let file_id = label.span.span.file_id;
+            if sections
+                .iter()
+                .any(|(span, _, _)| span.span.file_id == file_id)
             {
-                let mut current_col = if line_idx == start_loc.line {
-                    start_loc.col
-                } else {
-                    0
-                };
-
-                let mut handle_color_event = |buffer: &mut Buffer, col: usize| {
-                    while let Some(event) = events_iter.peek() {
-                        if event.line == line_idx && event.col == col {
-                            color_stack.handle_event(event);
-                            events_iter.next();
-
-                            let spec = color_stack.current_spec();
-                            buffer.set_color(&spec).map_err(|e| warn!("{}", e)).ok();
-                        } else {
-                            break;
-                        }
-                    }
-                };
+                continue;
+            }
+            let content = match self.crate_data.file_id_to_content.get(&file_id) {
+                Some(content) => content.as_str(),
+                None => "",
+            };
+            sections.push((
+                label.span,
+                content,
+                shown_lines(content, &labels, file_id, None),
+            ));
+        }
 
-                for ch in line_content.chars() {
-                    handle_color_event(&mut buffer, current_col);
-                    write!(buffer, "{}", ch).ok();
-                    current_col += 1;
-                }
+        let gutter_width = sections
+            .iter()
+            .filter_map(|(_, _, shown)| shown.iter().last())
+            .max()
+            .map_or(1, |line| line.to_string().len());
+        let empty_gutter = " ".repeat(gutter_width);
 
-                // Handle reset
-                handle_color_event(&mut buffer, current_col);
-                write!(buffer, "\n").ok();
+        let mut buffer = if color {
+            Buffer::ansi()
+        } else {
+            Buffer::no_color()
+        };
+        for (section_span, content, shown) in sections.iter() {
+            let file_id = section_span.span.file_id;
+            if file_id != main_file {
+                writeln!(
+                    buffer,
+                    "{}--> {}",
+                    empty_gutter,
+                    span_to_string(self.crate_data, section_span)
+                )
+                .ok();
+            }
+            writeln!(buffer, "{} |", empty_gutter).ok();
+            if shown.is_empty() {
+                writeln!(buffer, "{} = unable to get the source", empty_gutter).ok();
             }
 
-            // Reset the color after printing the span just in case
-            buffer
-                .set_color(ColorSpec::new().set_reset(true))
-                .map_err(|e| warn!("{}", e))
+            let source_lines: Vec<&str> = content.lines().collect();
+            let mut previous_line = None;
+            for &line in shown.iter() {
+                if previous_line.map_or(false, |previous| line > previous + 1) {
+                    writeln!(buffer, "{:>width$}", "...", width = gutter_width).ok();
+                }
+                previous_line = Some(line);
+
+                let text = source_lines[line - 1];
+                writeln!(
+                    buffer,
+                    "{:>width$} | {}",
+                    line,
+                    expand_tabs(text),
+                    width = gutter_width
+                )
                 .ok();
 
-            String::from_utf8_lossy(buffer.as_slice()).into()
-        } else {
-            format!("Unable to get span for {:?}", self.main_span)
+                for label in labels.iter().filter(|label| {
+                    label.span.span.file_id == file_id && label.span.span.beg.line == line
+                }) {
+                    let beg = visual_col(text, label.span.span.beg.col);
+                    let end = if label.span.span.end.line == line {
+                        visual_col(text, label.span.span.end.col)
+                    } else {
+                        expand_tabs(text).chars().count()
+                    };
+                    let mark = match label.kind {
+                        LabelKind::Primary => "^",
+                        LabelKind::Secondary => "-",
+                    };
+
+                    write!(buffer, "{} | {}", empty_gutter, " ".repeat(beg)).ok();
+                    let mut spec = ColorSpec::new();
+                    spec.set_fg(Some(label.color))
+                        .set_bold(label.kind == LabelKind::Primary);
+                    buffer.set_color(&spec).map_err(|e| warn!("{}", e)).ok();
+                    write!(buffer, "{}", mark.repeat(end.saturating_sub(beg).max(1))).ok();
+                    if !label.message.is_empty() {
+                        write!(buffer, " {}", label.message).ok();
+                    }
+                    buffer
+                        .set_color(ColorSpec::new().set_reset(true))
+                        .map_err(|e| warn!("{}", e))
+                        .ok();
+                    writeln!(buffer).ok();
+                }
+            }
+            writeln!(buffer, "{} |", empty_gutter).ok();
         }
+
+        String::from_utf8_lossy(buffer.as_slice()).into()
     }
 }
 
@@ -210,6 +211,52 @@ impl<'tcx> ToString for ColorSpan<'tcx> {
     }
 }
 
+/// The 1-based numbers of the lines of `content` to show: `first_line`, and
+/// the lines of the labels in `file_id` with their context
+fn shown_lines(
+    content: &str,
+    labels: &[SpanLabel],
+    file_id: FileId,
+    first_line: Option<usize>,
+) -> BTreeSet<usize> {
+    let mut shown: BTreeSet<usize> = first_line.into_iter().collect();
+    for label in labels
+        .iter()
+        .filter(|label| label.span.span.file_id == file_id)
+    {
+        let line = label.span.span.beg.line;
+        shown.extend(line.saturating_sub(CONTEXT_LINES).max(1)..=line + CONTEXT_LINES);
+    }
+    // Line 0 is an unknown location
+    let line_count = content.lines().count();
+    shown.retain(|line| (1..=line_count).contains(line));
+    shown
+}
+
+const TAB_WIDTH: usize = 4;
+
+fn expand_tabs(line: &str) -> String {
+    line.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+/// The column of the character `col` of `line` once the tabs are expanded
+fn visual_col(line: &str, col: usize) -> usize {
+    line.chars()
+        .take(col)
+        .map(|ch| if ch == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+/// Returns the statements and the terminator of a block, one per line.
+pub fn format_block(block: &BlockData, fmt: &FmtCtx) -> Vec<String> {
+    block
+        .statements
+        .iter()
+        .map(|st| st.fmt_with_ctx(fmt))
+        .chain(std::iter::once(block.terminator.fmt_with_ctx(fmt)))
+        .collect()
+}
+
 // TODO: move to Charon
 pub fn span_to_snippet(crate_data: &TranslatedCrate, span: &Span) -> Result<Vec<String>, ()> {
     let content = crate_data
@@ -218,25 +265,31 @@ pub fn span_to_snippet(crate_data: &TranslatedCrate, span: &Span) -> Result<Vec<
         .ok_or(())?;
     let content: Vec<String> = content.lines().map(|s| s.to_string()).collect();
     // This is not meant to be efficient
-    let mut lines: Vec<_> = Vec::from(&content[span.span.beg.line - 1..span.span.end.line - 1]);
-    if lines.len() <= 0 {
+    let (beg, end) = (span.span.beg, span.span.end);
+    if beg.line == 0 || beg.line > end.line || end.line > content.len() {
         return Err(());
     }
-    // Shift the columns
+    let mut lines: Vec<_> = Vec::from(&content[beg.line - 1..end.line]);
+    // Shift the columns. The end column is exclusive, and must be applied
+    // first because it is relative to the start of the line.
     let lines_len = lines.len();
     use take_mut::take;
-    take(&mut lines[0], |l| {
-        l.chars().skip(span.span.beg.col).collect()
+    take(&mut lines[lines_len - 1], |l| {
+        l.chars().take(end.col).collect()
     });
-    // TODO: it seems the end column is imprecise
-    /*take(&mut lines[lines_len - 1], |l| {
-        l.chars().take(span.span.end.col).collect()
-    });*/
+    take(&mut lines[0], |l| l.chars().skip(beg.col).collect());
     Ok(lines)
 }
 
+/// The source of `span`, or its location if the source is not available
+fn snippet_or_location(crate_data: &TranslatedCrate, span: &Span) -> String {
+    span_to_snippet(crate_data, span)
+        .map(|lines| lines.join("\n"))
+        .unwrap_or_else(|()| span_to_string(crate_data, span))
+}
+
 pub fn print_span(crate_data: &TranslatedCrate, span: &Span) {
-    let snippet = span_to_snippet(crate_data, span).unwrap().join("\n");
+    let snippet = snippet_or_location(crate_data, span);
     eprintln!("{:?}\n{}\n", span, snippet);
 }
 
@@ -257,7 +310,7 @@ pub fn span_to_string(crate_data: &TranslatedCrate, span: &Span) -> String {
 pub fn print_span_to_file(crate_data: &TranslatedCrate, span: &Span, output_name: &str) {
     let sysroot = compile_time_sysroot().expect("Failed to fetch sysroot");
     let filename = format!("{}/logs/{}", sysroot, output_name);
-    let snippet = span_to_snippet(crate_data, span).unwrap().join("\n");
+    let snippet = snippet_or_location(crate_data, span);
     let content = format!("{}\n{}\n", span_to_string(crate_data, span), snippet);
     std::fs::write(filename, content).expect("Unable to write file");
 }
@@ -308,3 +361,144 @@ pub fn print_mir_to_file<'tcx>(tcx: TyCtxt<'tcx>, instance: Instance<'tcx>, outp
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use charon_lib::ast::meta::RawSpan;
+    use std::path::PathBuf;
+
+    /// A crate with the given files, and their ids.
+    fn crate_with_files(files: &[(&str, &str)]) -> (TranslatedCrate, Vec<FileId>) {
+        let mut crate_data = TranslatedCrate::default();
+        let file_ids = files
+            .iter()
+            .map(|(name, content)| {
+                let file_id = crate_data
+                    .id_to_file
+                    .push(FileName::Local(PathBuf::from(name)));
+                crate_data
+                    .file_id_to_content
+                    .insert(file_id, content.to_string());
+                file_id
+            })
+            .collect();
+        (crate_data, file_ids)
+    }
+
+    /// A span from `(line, col)` to `(line, col)`, the end column is exclusive.
+    fn span(file_id: FileId, beg: (usize, usize), end: (usize, usize)) -> Span {
+        Span {
+            span: RawSpan {
+                file_id,
+                beg: Loc {
+                    line: beg.0,
+                    col: beg.1,
+                },
+                end: Loc {
+                    line: end.0,
+                    col: end.1,
+                },
+            },
+            generated_from_span: None,
+        }
+    }
+
+    fn label(span: Span) -> SpanLabel {
+        SpanLabel {
+            kind: LabelKind::Primary,
+            color: Color::Red,
+            span,
+            message: String::new(),
+        }
+    }
+
+    const SOURCE: &str = "fn f() {\n    let a = 1;\n    let b = g(a);\n    h(b);\n}\n";
+
+    #[test]
+    fn visual_col_expands_tabs() {
+        assert_eq!(visual_col("\tlet a", 0), 0);
+        assert_eq!(visual_col("\tlet a", 1), TAB_WIDTH);
+        assert_eq!(visual_col("\tlet a", 4), TAB_WIDTH + 3);
+        assert_eq!(visual_col("let\ta", 4), 3 + TAB_WIDTH);
+        assert_eq!(expand_tabs("\ta"), format!("{}a", " ".repeat(TAB_WIDTH)));
+    }
+
+    #[test]
+    fn shown_lines_with_context() {
+        let (_, files) = crate_with_files(&[("a.rs", SOURCE)]);
+        let labels = [label(span(files[0], (3, 12), (3, 16)))];
+        let shown = shown_lines(SOURCE, &labels, files[0], Some(1));
+        assert_eq!(shown.into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+
+        // The context stops at the last line
+        let labels = [label(span(files[0], (5, 0), (5, 1)))];
+        let shown = shown_lines(SOURCE, &labels, files[0], None);
+        assert_eq!(shown.into_iter().collect::<Vec<_>>(), vec![4, 5]);
+    }
+
+    #[test]
+    fn shown_lines_ignore_line_zero() {
+        let (_, files) = crate_with_files(&[("a.rs", SOURCE)]);
+        let labels = [label(span(files[0], (0, 0), (0, 0)))];
+        let shown = shown_lines(SOURCE, &labels, files[0], Some(0));
+        assert_eq!(shown.into_iter().collect::<Vec<_>>(), vec![1]);
+
+        let shown = shown_lines(SOURCE, &[], files[0], Some(0));
+        assert!(shown.is_empty());
+    }
+
+    #[test]
+    fn snippet_end_column_is_exclusive() {
+        let (crate_data, files) = crate_with_files(&[("a.rs", SOURCE)]);
+        let snippet = span_to_snippet(&crate_data, &span(files[0], (3, 12), (3, 16)));
+        assert_eq!(snippet, Ok(vec!["g(a)".to_owned()]));
+
+        // The end column is relative to the start of the last line
+        let snippet = span_to_snippet(&crate_data, &span(files[0], (3, 12), (4, 8)));
+        assert_eq!(snippet, Ok(vec!["g(a);".to_owned(), "    h(b)".to_owned()]));
+
+        assert!(span_to_snippet(&crate_data, &span(files[0], (0, 0), (1, 2))).is_err());
+        assert!(span_to_snippet(&crate_data, &span(files[0], (4, 0), (9, 0))).is_err());
+    }
+
+    #[test]
+    fn annotated_string() {
+        let (crate_data, files) = crate_with_files(&[("a.rs", SOURCE), ("b.rs", "m!();\n")]);
+        let mut color_span = ColorSpan::new(&crate_data, span(files[0], (1, 0), (5, 1))).unwrap();
+        color_span.add_label(
+            LabelKind::Primary,
+            Color::Red,
+            span(files[0], (3, 12), (3, 16)),
+            "call",
+        );
+        color_span.add_label(
+            LabelKind::Secondary,
+            Color::Blue,
+            span(files[1], (1, 0), (1, 2)),
+            "macro",
+        );
+
+        let rendered = color_span.to_annotated_string(false);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                " |",
+                "1 | fn f() {",
+                "2 |     let a = 1;",
+                "3 |     let b = g(a);",
+                " |             ^^^^ call",
+                "4 |     h(b);",
+                " |",
+                " --> b.rs:1:0-1:2",
+                " |",
+                "1 | m!();",
+                " | -- macro",
+                " |",
+            ]
+        );
+        assert!(!rendered.contains('\u{1b}'));
+        assert!(color_span.to_annotated_string(true).contains('\u{1b}'));
+    }
+}
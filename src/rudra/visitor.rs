@@ -0,0 +1,73 @@
+//! Visitors over the function bodies.
+
+use crate::rudra::context::RudraCtxt;
+use crate::rudra::local_defs;
+
+use charon_lib::ullbc_ast::{
+    Call, FnOperand, FnPtr, FunDecl, FunId, FunIdOrTraitMethodRef, Locals, RawStatement,
+};
+
+/// Checks whether a function contains unsafe code. Charon doesn't record the
+/// `unsafe` blocks: we look for the operations that require one, i.e. raw
+/// pointer dereferences and calls to unsafe functions.
+pub struct ContainsUnsafe<'tcx> {
+    rcx: RudraCtxt<'tcx>,
+    locals: &'tcx Locals,
+}
+
+impl<'tcx> ContainsUnsafe<'tcx> {
+    pub fn contains_unsafe(rcx: RudraCtxt<'tcx>, decl: &FunDecl) -> bool {
+        if decl.signature.is_unsafe {
+            return true;
+        }
+
+        let body = match decl
+            .body
+            .as_ref()
+            .ok()
+            .and_then(|body_id| rcx.crate_data.bodies.get(*body_id))
+            .and_then(|body| body.as_unstructured())
+        {
+            Some(body) => body,
+            None => return false,
+        };
+        let visitor = ContainsUnsafe {
+            rcx,
+            locals: &body.locals,
+        };
+        body.body.iter().any(|block| {
+            block
+                .statements
+                .iter()
+                .any(|st| visitor.is_unsafe_statement(&st.content))
+        })
+    }
+
+    fn is_unsafe_statement(&self, st: &RawStatement) -> bool {
+        let derefs_raw_ptr = local_defs::statement_places(st)
+            .iter()
+            .any(|place| self.rcx.derefs_raw_ptr(self.locals, place));
+        derefs_raw_ptr || self.calls_unsafe_fn(st)
+    }
+
+    /// Calls to an unsafe function or to an unsafe trait method, like
+    /// `GlobalAlloc::alloc`: the unsafety is declared by the trait.
+    fn calls_unsafe_fn(&self, st: &RawStatement) -> bool {
+        let callee_did = match st {
+            RawStatement::Call(Call {
+                func: FnOperand::Regular(FnPtr { func, .. }),
+                ..
+            }) => match func {
+                FunIdOrTraitMethodRef::Fun(FunId::Regular(callee_did)) => callee_did,
+                FunIdOrTraitMethodRef::Trait(_, _, method_did) => method_did,
+                _ => return false,
+            },
+            _ => return false,
+        };
+        self.rcx
+            .crate_data
+            .fun_decls
+            .get(*callee_did)
+            .map_or(false, |decl| decl.signature.is_unsafe)
+    }
+}
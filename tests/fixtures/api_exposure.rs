@@ -0,0 +1,36 @@
+//! Reports are ranked by the exposure of the function to the public API.
+#![crate_type = "lib"]
+use std::mem;
+
+/// Private, and never called from the public API: the report is lowered.
+#[allow(dead_code)]
+fn unused_byte_to_bool(b: u8) -> bool {
+    unsafe { mem::transmute(b) }
+}
+
+/// Private, but called from the public API: the report keeps its level.
+fn byte_to_bool(b: u8) -> bool {
+    unsafe { mem::transmute(b) }
+}
+
+pub fn parse_flag(b: u8) -> bool {
+    byte_to_bool(b)
+}
+
+mod inner {
+    use std::mem;
+
+    /// Public in a private module, and never called from the public API: the
+    /// report is lowered.
+    #[allow(dead_code)]
+    pub fn hidden_byte_to_bool(b: u8) -> bool {
+        unsafe { mem::transmute(b) }
+    }
+
+    /// Public in a private module, but re-exported: the report keeps its level.
+    pub fn exported_byte_to_bool(b: u8) -> bool {
+        unsafe { mem::transmute(b) }
+    }
+}
+
+pub use inner::exported_byte_to_bool;
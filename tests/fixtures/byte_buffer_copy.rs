@@ -0,0 +1,14 @@
+//! Negative case: byte-buffer code built on `copy_nonoverlapping::<u8>`,
+//! `slice::from_raw_parts` and `get_unchecked` on Copy elements.
+#![crate_type = "lib"]
+use std::ptr;
+
+pub fn append_with<F: FnMut(&[u8])>(buf: &mut Vec<u8>, data: &[u8], mut f: F) {
+    buf.reserve(data.len());
+    unsafe {
+        let len = buf.len();
+        ptr::copy_nonoverlapping(data.as_ptr(), buf.as_mut_ptr().add(len), data.len());
+        f(std::slice::from_raw_parts(buf.as_ptr(), len));
+        f(&[*data.get_unchecked(0)]);
+    }
+}
@@ -0,0 +1,29 @@
+//! Closures defined in the crate are analyzed as callees, and the closures
+//! provided by the caller are reported as user code.
+#![crate_type = "lib"]
+use std::ptr;
+
+/// The closure is provided by the caller, and may panic while `p` is duplicated.
+pub fn read_then_call<T, F: FnOnce(&T)>(p: *const T, f: F) -> T {
+    unsafe {
+        let value = ptr::read(p);
+        f(&value);
+        value
+    }
+}
+
+/// Negative case: the local closure doesn't panic, as it calls nothing.
+pub fn read_then_len(p: *const String, other: Option<&String>) -> (String, Option<usize>) {
+    unsafe {
+        let value = ptr::read(p);
+        (value, other.map(|_| 0))
+    }
+}
+
+/// The local closure panics on invalid input while `p` is duplicated.
+pub fn read_then_parse(p: *const String, other: Option<&String>) -> (String, Option<u32>) {
+    unsafe {
+        let value = ptr::read(p);
+        (value, other.map(|s| s.parse().unwrap()))
+    }
+}
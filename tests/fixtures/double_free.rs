@@ -0,0 +1,49 @@
+//! Values duplicated with `ptr::read` and dropped while the original is still
+//! owned, and the write-back idiom that makes them safe.
+#![crate_type = "lib"]
+use std::ptr;
+
+/// The caller drops `*v` again.
+pub fn read_from_ref<T>(v: &mut T) {
+    unsafe {
+        let _x = ptr::read(v);
+    }
+}
+
+/// Both `x` and its duplicate are dropped at the end of the function.
+pub fn read_from_local<T>(x: T) {
+    unsafe {
+        let _y = ptr::read(&x as *const T);
+    }
+}
+
+/// Negative case: a new value is written back before the duplicate is dropped.
+pub fn reset<T: Default>(v: &mut T) {
+    unsafe {
+        let _old = ptr::read(v);
+        ptr::write(v, T::default());
+    }
+}
+
+pub struct RawBuf<T> {
+    ptr: *mut T,
+    len: usize,
+}
+
+impl<T> RawBuf<T> {
+    /// Negative case: the value is read and dropped through a raw pointer
+    /// stored in a field of `self`, so `self` doesn't own it.
+    pub fn drop_at(&mut self, i: usize) {
+        assert!(i < self.len);
+        unsafe {
+            let _x = ptr::read(self.ptr.add(i));
+        }
+    }
+
+    /// Negative case: same, without the offset.
+    pub fn drop_first(&mut self) {
+        unsafe {
+            let _x = ptr::read(self.ptr);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Destructors that may panic, or that call user-provided code after moving a
+//! field out of `self`.
+#![crate_type = "lib"]
+use std::mem::ManuallyDrop;
+
+pub struct Guard {
+    token: Option<Box<u8>>,
+}
+
+/// `unwrap` panics if the token was already taken.
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let _token = self.token.take().unwrap();
+    }
+}
+
+pub struct Ring {
+    slots: [u8; 4],
+    head: usize,
+}
+
+/// The bounds check on `head` may fail.
+impl Drop for Ring {
+    fn drop(&mut self) {
+        self.slots[self.head] = 0;
+    }
+}
+
+pub struct OnDrop<T, F: FnMut(T)> {
+    value: ManuallyDrop<T>,
+    callback: F,
+}
+
+/// `value` is moved out of `self` before calling the user-provided callback:
+/// if the callback panics, `value` is dropped twice.
+impl<T, F: FnMut(T)> Drop for OnDrop<T, F> {
+    fn drop(&mut self) {
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        (self.callback)(value);
+    }
+}
+
+pub struct Counter {
+    count: u32,
+}
+
+/// Negative case: nothing can panic.
+impl Drop for Counter {
+    fn drop(&mut self) {
+        self.count = 0;
+    }
+}
@@ -0,0 +1,10 @@
+//! The vector rebuilt with `Vec::from_raw_parts` takes ownership of elements
+//! that are then dropped through generic drop glue.
+#![crate_type = "lib"]
+use std::ptr;
+
+pub unsafe fn drop_first<T>(p: *mut T, len: usize, cap: usize) -> Vec<T> {
+    let mut v = Vec::from_raw_parts(p, len, cap);
+    ptr::drop_in_place(v.as_mut_ptr());
+    v
+}
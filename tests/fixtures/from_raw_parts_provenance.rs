@@ -0,0 +1,32 @@
+//! Pointers passed to `Vec::from_raw_parts` which don't come from a `Vec` of
+//! the same element type and capacity.
+#![crate_type = "lib"]
+use std::mem;
+
+/// The slice may not be heap allocated.
+pub unsafe fn from_slice(s: &mut [u32]) -> Vec<u32> {
+    Vec::from_raw_parts(s.as_mut_ptr(), s.len(), s.len())
+}
+
+/// The allocation has `u8` elements.
+pub unsafe fn cast_elements(mut v: Vec<u8>) -> Vec<u32> {
+    let p = v.as_mut_ptr() as *mut u32;
+    mem::forget(v);
+    Vec::from_raw_parts(p, 0, 4)
+}
+
+/// The length is used as capacity.
+pub unsafe fn len_as_capacity(mut v: Vec<u32>) -> Vec<u32> {
+    let p = v.as_mut_ptr();
+    let len = v.len();
+    mem::forget(v);
+    Vec::from_raw_parts(p, len, len)
+}
+
+/// Negative case: same element type and capacity.
+pub unsafe fn roundtrip() -> Vec<u32> {
+    let mut v = Vec::<u32>::with_capacity(16);
+    let p = v.as_mut_ptr();
+    mem::forget(v);
+    Vec::from_raw_parts(p, 0, 16)
+}
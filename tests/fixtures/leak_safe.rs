@@ -0,0 +1,37 @@
+//! Negative cases: idioms giving up the ownership of a duplicated value before
+//! calling user code.
+#![crate_type = "lib"]
+use std::mem::{self, ManuallyDrop};
+use std::ptr;
+
+/// `set_len(len - 1)` after moving the last element out.
+pub fn pop_with<T, F: FnMut()>(v: &mut Vec<T>, mut f: F) -> Option<T> {
+    if v.is_empty() {
+        return None;
+    }
+    unsafe {
+        let len = v.len();
+        let last = ptr::read(v.as_ptr().add(len - 1));
+        v.set_len(len - 1);
+        f();
+        Some(last)
+    }
+}
+
+/// `mem::forget` of the source after copying it out.
+pub fn move_to_with<T, F: FnMut()>(src: T, dst: *mut T, mut f: F) {
+    unsafe {
+        ptr::copy_nonoverlapping(&src, dst, 1);
+        mem::forget(src);
+        f();
+    }
+}
+
+/// `ManuallyDrop` wrapping of the duplicate.
+pub fn read_with<T, F: FnMut()>(p: *const T, mut f: F) -> ManuallyDrop<T> {
+    unsafe {
+        let x = ManuallyDrop::new(ptr::read(p));
+        f();
+        x
+    }
+}
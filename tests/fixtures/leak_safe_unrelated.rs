@@ -0,0 +1,47 @@
+//! Leak-safe operations only give up the ownership of the values they concern:
+//! the other duplicated values are still reported.
+#![crate_type = "lib"]
+use std::mem;
+use std::ptr;
+
+/// `set_len(0)` leaks the elements of the vector, not the value read from `p`.
+pub fn clear_then_call<T, F: FnMut()>(v: &mut Vec<T>, p: *const T, mut f: F) -> T {
+    unsafe {
+        let x = ptr::read(p);
+        v.set_len(0);
+        f();
+        x
+    }
+}
+
+/// The new length is computed from another vector.
+pub fn pop_other_len<T, F: FnMut()>(v: &mut Vec<T>, w: &Vec<T>, mut f: F) -> T {
+    unsafe {
+        let len = v.len();
+        let last = ptr::read(v.as_ptr().add(len - 1));
+        v.set_len(w.len() - 1);
+        f();
+        last
+    }
+}
+
+/// No element is moved out of the shrunk vector.
+pub fn shrink_then_call<T, F: FnMut()>(v: &mut Vec<T>, p: *const T, mut f: F) -> T {
+    unsafe {
+        let x = ptr::read(p);
+        v.set_len(v.len() - 1);
+        f();
+        x
+    }
+}
+
+/// Only one of the two duplicates is forgotten.
+pub fn forget_one<T, F: FnMut()>(p: *const T, q: *const T, mut f: F) -> T {
+    unsafe {
+        let x = ptr::read(p);
+        let y = ptr::read(q);
+        mem::forget(x);
+        f();
+        y
+    }
+}
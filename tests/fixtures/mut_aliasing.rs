@@ -0,0 +1,32 @@
+//! `&mut` references created from raw pointers: two live references to the
+//! same value, and a `&mut` laundered out of a shared reference.
+#![crate_type = "lib"]
+
+pub struct Holder<T> {
+    ptr: *mut T,
+}
+
+/// `&mut` derived from `&Holder` through the raw pointer it stores.
+pub fn get_mut<T>(holder: &Holder<T>) -> &mut T {
+    unsafe { &mut *holder.ptr }
+}
+
+/// `a` is still used after `b` has been created.
+pub fn two_mut<T>(p: *mut T, x: T, y: T) {
+    unsafe {
+        let a = &mut *p;
+        let b = &mut *p;
+        *b = y;
+        *a = x;
+    }
+}
+
+/// Negative case: `a` is not used anymore when `b` is created.
+pub fn sequential_mut<T>(p: *mut T, x: T, y: T) {
+    unsafe {
+        let a = &mut *p;
+        *a = x;
+        let b = &mut *p;
+        *b = y;
+    }
+}
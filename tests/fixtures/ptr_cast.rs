@@ -0,0 +1,18 @@
+//! Raw pointer casts to types with a larger alignment or size, as found in
+//! byte-buffer parsing code.
+#![crate_type = "lib"]
+
+/// `buf` may be misaligned and shorter than 8 bytes.
+pub fn read_u64(buf: &[u8]) -> u64 {
+    unsafe { *(buf.as_ptr() as *const u64) }
+}
+
+/// Negative case: unaligned reads are not checked.
+pub fn read_u64_unaligned(buf: &[u8]) -> u64 {
+    unsafe { (buf.as_ptr() as *const u64).read_unaligned() }
+}
+
+/// Negative case: the target type is smaller.
+pub fn first_byte(x: &u64) -> u8 {
+    unsafe { *(x as *const u64 as *const u8) }
+}
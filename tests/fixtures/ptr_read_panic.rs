@@ -0,0 +1,12 @@
+//! `ptr::read` duplicates the value behind `v`, then the user-provided closure
+//! may panic before the new value is written back: `*v` is dropped twice.
+#![crate_type = "lib"]
+use std::ptr;
+
+pub fn replace_with<T, F: FnOnce(T) -> T>(v: &mut T, f: F) {
+    unsafe {
+        let old = ptr::read(v);
+        let new = f(old);
+        ptr::write(v, new);
+    }
+}
@@ -0,0 +1,10 @@
+//! Negative case: reading a `u32` out of a pointer cannot duplicate ownership.
+#![crate_type = "lib"]
+use std::ptr;
+
+pub fn read_then_call<F: Fn(u32)>(p: *const u32, f: F) {
+    unsafe {
+        let x = ptr::read(p);
+        f(x);
+    }
+}
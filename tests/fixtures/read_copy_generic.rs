@@ -0,0 +1,11 @@
+//! Negative case: `T: Copy`, so the duplicate created by `ptr::read` has no
+//! drop glue and a panic in `f` is harmless.
+#![crate_type = "lib"]
+use std::ptr;
+
+pub fn read_then_call<T: Copy, F: Fn(T)>(p: *const T, f: F) {
+    unsafe {
+        let x = ptr::read(p);
+        f(x);
+    }
+}
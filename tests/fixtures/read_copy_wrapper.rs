@@ -0,0 +1,28 @@
+//! `Copy` through conditional impls and arrays: only the `Box` read duplicates
+//! ownership.
+#![crate_type = "lib"]
+use std::ptr;
+
+#[derive(Clone, Copy)]
+pub struct Wrapper<T>(pub T);
+
+pub fn read_wrapper<T: Copy, F: Fn(Wrapper<T>)>(p: *const Wrapper<T>, f: F) {
+    unsafe {
+        let x = ptr::read(p);
+        f(x);
+    }
+}
+
+pub fn read_array<T: Copy, F: Fn([T; 4])>(p: *const [T; 4], f: F) {
+    unsafe {
+        let x = ptr::read(p);
+        f(x);
+    }
+}
+
+pub fn read_box<T: Copy, F: Fn(Box<T>)>(p: *const Box<T>, f: F) {
+    unsafe {
+        let x = ptr::read(p);
+        f(x);
+    }
+}
@@ -0,0 +1,14 @@
+//! The length is set before the elements are initialized, then `T::clone`
+//! may panic: the vector drops uninitialized elements.
+#![crate_type = "lib"]
+
+pub fn filled<T: Clone>(len: usize, elem: &T) -> Vec<T> {
+    let mut v = Vec::with_capacity(len);
+    unsafe {
+        v.set_len(len);
+    }
+    for i in 0..len {
+        v[i] = elem.clone();
+    }
+    v
+}
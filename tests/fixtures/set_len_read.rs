@@ -0,0 +1,14 @@
+//! The length is set before the bytes are initialized, then they are passed to
+//! `Read::read_exact`, which is user code: Copy elements don't make the
+//! uninitialized memory safe to read.
+#![crate_type = "lib"]
+use std::io::{self, Read};
+
+pub fn read_len<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len);
+    unsafe {
+        buf.set_len(len);
+    }
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
@@ -0,0 +1,9 @@
+//! Negative case: `set_len(0)` only leaks the elements, which is safe.
+#![crate_type = "lib"]
+
+pub fn clear_with<T, F: FnMut()>(v: &mut Vec<T>, mut f: F) {
+    unsafe {
+        v.set_len(0);
+    }
+    f();
+}
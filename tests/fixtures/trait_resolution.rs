@@ -0,0 +1,36 @@
+//! Trait method calls are resolved to the method of the impl when it is known:
+//! the call is only a sink if that method may call user code.
+#![crate_type = "lib"]
+use std::marker::PhantomData;
+use std::ptr;
+
+pub struct Handle<T> {
+    id: usize,
+    _marker: PhantomData<T>,
+}
+
+/// Requires `T: Clone`, but never calls `T::clone` nor an external function.
+impl<T: Clone> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle {
+            id: self.id,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Negative case: `Handle::<T>::clone` doesn't call user code.
+pub fn read_then_clone_handle<T: Clone>(p: *const T, handle: &Handle<T>) -> (T, Handle<T>) {
+    unsafe {
+        let value = ptr::read(p);
+        (value, handle.clone())
+    }
+}
+
+/// `T::clone` is user code, which may panic while `p` is duplicated.
+pub fn read_then_clone_value<T: Clone>(p: *const T, other: &T) -> (T, T) {
+    unsafe {
+        let value = ptr::read(p);
+        (value, other.clone())
+    }
+}
@@ -0,0 +1,39 @@
+//! Transmutes flagged from their source and target types, and a transmute
+//! between types with the same validity invariants.
+#![crate_type = "lib"]
+#![allow(mutable_transmutes)]
+use std::mem;
+
+pub fn shared_to_mut(x: &u32) -> &mut u32 {
+    unsafe { mem::transmute::<&u32, &mut u32>(x) }
+}
+
+pub fn int_to_ref(addr: usize) -> &'static u32 {
+    unsafe { mem::transmute::<usize, &'static u32>(addr) }
+}
+
+pub fn byte_to_bool(b: u8) -> bool {
+    unsafe { mem::transmute::<u8, bool>(b) }
+}
+
+/// Negative case: any bit pattern of `u32` is a valid `[u8; 4]`.
+pub fn to_bytes(x: u32) -> [u8; 4] {
+    unsafe { mem::transmute::<u32, [u8; 4]>(x) }
+}
+
+pub fn extend_lifetime<'a>(x: &'a u32) -> &'static u32 {
+    unsafe { mem::transmute::<&'a u32, &'static u32>(x) }
+}
+
+/// Negative case: `'static` outlives any lifetime.
+pub fn shorten_lifetime<'a>(x: &'static u32) -> &'a u32 {
+    unsafe { mem::transmute::<&'static u32, &'a u32>(x) }
+}
+
+pub fn swap_lifetime<'a, 'b>(x: &'a u32, _y: &'b u32) -> &'b u32 {
+    unsafe { mem::transmute::<&'a u32, &'b u32>(x) }
+}
+
+pub fn shared_to_mut_static<'a>(x: &'a u32) -> &'static mut u32 {
+    unsafe { mem::transmute::<&'a u32, &'static mut u32>(x) }
+}
@@ -0,0 +1,50 @@
+//! Uninitialized memory exposure: `assume_init` and `zeroed` on invalid values
+//! are reported, initialized memory and plain values are not.
+#![crate_type = "lib"]
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+
+pub fn assume_init_uninit<T>() -> T {
+    unsafe { MaybeUninit::uninit().assume_init() }
+}
+
+pub fn assume_init_written<T>(x: T) -> T {
+    let mut m = MaybeUninit::uninit();
+    m.write(x);
+    unsafe { m.assume_init() }
+}
+
+pub fn zeroed_ref() -> &'static u8 {
+    unsafe { mem::zeroed() }
+}
+
+pub fn zeroed_int() -> u64 {
+    unsafe { mem::zeroed() }
+}
+
+pub fn zero_filled(n: usize) -> Vec<u8> {
+    let mut v = Vec::with_capacity(n);
+    unsafe {
+        ptr::write_bytes(v.as_mut_ptr(), 0, n);
+        v.set_len(n);
+    }
+    v
+}
+
+pub fn write_other(n: usize, other: &mut [u8]) -> Vec<u8> {
+    let mut v = Vec::with_capacity(n);
+    unsafe {
+        ptr::write(other.as_mut_ptr(), 0);
+        v.set_len(n);
+    }
+    v
+}
+
+pub struct Plain {
+    pub a: u32,
+    pub b: u64,
+}
+
+pub fn zeroed_plain() -> Plain {
+    unsafe { mem::zeroed() }
+}
@@ -0,0 +1,43 @@
+//! `unsafe impl`s listed by the auditor, with and without the bounds
+//! required by `Send`/`Sync`, and the impls of the safe `Unpin` trait.
+#![crate_type = "lib"]
+
+pub struct Wrapper<T>(*mut T);
+
+/// `T` is not bounded by `Send`.
+unsafe impl<T> Send for Wrapper<T> {}
+
+unsafe impl<T: Sync> Sync for Wrapper<T> {}
+
+pub struct Lock<T>(std::cell::UnsafeCell<T>);
+
+/// `T: Send` is enough, as `Lock` only hands out exclusive access to `T`.
+#[allow(unused)]
+unsafe
+impl<T: Send> Sync for Lock<T> {}
+
+pub struct Shared<T>(std::cell::UnsafeCell<T>);
+
+impl<T> Shared<T> {
+    pub fn get(&self) -> &T {
+        unsafe { &*self.0.get() }
+    }
+}
+
+/// `T: Send` is not enough, as `Shared::get` hands out `&T` to several threads.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+pub struct Pinned<T>(T);
+
+impl<T> Unpin for Pinned<T> {}
+
+/// # Safety
+/// The all-zero bit pattern must be a valid value.
+pub unsafe trait Zeroable {}
+
+unsafe impl Zeroable for u32 {}
+
+pub trait Plain {}
+
+// Negative case: a safe impl, even with "unsafe impl" in a comment.
+impl<T> Plain for Lock<T> {}
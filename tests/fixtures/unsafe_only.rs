@@ -0,0 +1,22 @@
+//! Functions with and without unsafe code, for `--unsafe-only`: the unsafe
+//! operations are a raw pointer dereference through a generic field and a
+//! call to an unsafe trait method.
+#![crate_type = "lib"]
+use std::alloc::{GlobalAlloc, Layout};
+
+pub struct Holder<P> {
+    ptr: P,
+}
+
+pub fn read_field(holder: &Holder<*const u32>) -> u32 {
+    unsafe { *holder.ptr }
+}
+
+pub fn alloc_with<A: GlobalAlloc>(allocator: &A, layout: Layout) -> *mut u8 {
+    unsafe { allocator.alloc(layout) }
+}
+
+/// Negative case: no unsafe code.
+pub fn first_field(holder: &Holder<u32>) -> u32 {
+    holder.ptr
+}
@@ -0,0 +1,31 @@
+//! A bypassed value is only dropped twice if a sink unwinds while it is
+//! duplicated: calls that can neither panic nor run user code are not reported.
+#![crate_type = "lib"]
+use std::ptr;
+
+/// The `T: Clone` bound is never used and nothing is called: the call can't
+/// unwind. The external functions are assumed to panic.
+fn count<T: Clone>(_items: &[T]) -> usize {
+    0
+}
+
+/// `T::clone` is user code, which may panic.
+fn clone_first<T: Clone>(items: &[T]) -> Option<T> {
+    items.first().cloned()
+}
+
+/// Negative case: `count::<T>` returns normally.
+pub fn read_then_count<T: Clone>(p: *const T, items: &[T]) -> (T, usize) {
+    unsafe {
+        let value = ptr::read(p);
+        (value, count(items))
+    }
+}
+
+/// `clone_first::<T>` may unwind while `p` is duplicated.
+pub fn read_then_clone_first<T: Clone>(p: *const T, items: &[T]) -> (T, Option<T>) {
+    unsafe {
+        let value = ptr::read(p);
+        (value, clone_first(items))
+    }
+}
@@ -0,0 +1,343 @@
+//! Regression tests: run the analyzer on the `.ullbc` fixtures in `tests/fixtures`
+//! and compare the reports against the expected `.json` file next to each fixture.
+//!
+//! The `.ullbc` files are generated from the `.rs` sources with `make test-fixtures`.
+//! Run with `RUDRA_BLESS=1` to overwrite the expected reports with the current output.
+//!
+//! Only the `level`, `analyzer` and `description` fields of a report are compared,
+//! the rendered `source` of a report is checked by `rendered_report`.
+//! Expected reports may carry `"known_false_positive": true`: they are expected to
+//! be emitted, the flag only documents that the report is wrong.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+const COMPARED_FIELDS: &[&str] = &["level", "analyzer", "description"];
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+}
+
+/// Keep only the compared fields of each report, in a stable order.
+fn normalize(reports: &Value) -> Vec<Value> {
+    let mut reports: Vec<Value> = reports["reports"]
+        .as_array()
+        .expect("`reports` should be an array")
+        .iter()
+        .map(|report| {
+            let mut normalized = serde_json::Map::new();
+            for &field in COMPARED_FIELDS {
+                normalized.insert(field.to_string(), report[field].clone());
+            }
+            Value::Object(normalized)
+        })
+        .collect();
+    reports.sort_by_key(|report| report.to_string());
+    reports
+}
+
+/// Normalize `actual`, keeping the `known_false_positive` annotations of the
+/// reports that are already in the expected file.
+fn bless_reports(actual: &Value, expected_path: &Path) -> Vec<Value> {
+    let known_false_positives: Vec<Value> = fs::read_to_string(expected_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .map(|expected| {
+            let reports = expected["reports"].as_array().cloned().unwrap_or_default();
+            let flagged = reports
+                .into_iter()
+                .filter(|report| report["known_false_positive"] == json!(true))
+                .collect();
+            normalize(&json!({ "reports": flagged }))
+        })
+        .unwrap_or_default();
+
+    normalize(actual)
+        .into_iter()
+        .map(|mut report| {
+            if known_false_positives.contains(&report) {
+                report["known_false_positive"] = json!(true);
+            }
+            report
+        })
+        .collect()
+}
+
+/// Run the analyzer on `ullbc` and return the emitted reports.
+fn run_analyzer(ullbc: &Path) -> Value {
+    let name = ullbc.file_stem().unwrap().to_str().unwrap();
+    let report_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.json", name));
+    let _ = fs::remove_file(&report_path);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-charon-rudra"))
+        .arg("--file")
+        .arg(ullbc)
+        .env("RUDRA_REPORT_PATH", &report_path)
+        .output()
+        .expect("failed to run the analyzer");
+    assert!(
+        output.status.success(),
+        "analyzer failed on {}:\n{}",
+        ullbc.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The report file is only written when there is something to report
+    match fs::read_to_string(&report_path) {
+        Ok(content) => serde_json::from_str(&content).expect("invalid report file"),
+        Err(_) => json!({ "reports": [] }),
+    }
+}
+
+#[test]
+fn fixtures() {
+    let bless = std::env::var_os("RUDRA_BLESS").is_some();
+
+    let mut sources: Vec<PathBuf> = fs::read_dir(fixtures_dir())
+        .expect("cannot read the fixtures directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+        .collect();
+    sources.sort();
+    assert!(!sources.is_empty(), "no fixtures found");
+
+    let mut failures = Vec::new();
+    for source in sources.iter() {
+        let ullbc = source.with_extension("ullbc");
+        let expected_path = source.with_extension("json");
+        if !ullbc.exists() {
+            failures.push(format!(
+                "{}: missing fixture, run `make test-fixtures`",
+                ullbc.display()
+            ));
+            continue;
+        }
+
+        let actual = run_analyzer(&ullbc);
+        if bless {
+            let blessed = json!({ "reports": bless_reports(&actual, &expected_path) });
+            fs::write(
+                &expected_path,
+                serde_json::to_string_pretty(&blessed).unwrap() + "\n",
+            )
+            .expect("cannot write the expected reports");
+            continue;
+        }
+
+        let expected: Value = serde_json::from_str(
+            &fs::read_to_string(&expected_path)
+                .unwrap_or_else(|_| panic!("missing {}", expected_path.display())),
+        )
+        .expect("invalid expected report file");
+
+        let (actual, expected) = (normalize(&actual), normalize(&expected));
+        if actual != expected {
+            failures.push(format!(
+                "{}:\nexpected: {:#}\nactual: {:#}",
+                source.display(),
+                Value::Array(expected),
+                Value::Array(actual)
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
+
+/// Run the analyzer in inventory mode on `ullbc` and return the written file.
+fn run_inventory(ullbc: &Path, format: &str) -> String {
+    let name = ullbc.file_stem().unwrap().to_str().unwrap();
+    let inventory_path =
+        Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.inventory.{}", name, format));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-charon-rudra"))
+        .arg("--file")
+        .arg(ullbc)
+        .arg("--inventory")
+        .arg(&inventory_path)
+        .arg("--inventory-format")
+        .arg(format)
+        .output()
+        .expect("failed to run the analyzer");
+    assert!(
+        output.status.success(),
+        "inventory failed on {}:\n{}",
+        ullbc.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    fs::read_to_string(&inventory_path).expect("missing inventory file")
+}
+
+#[test]
+fn inventory() {
+    let ullbc = fixtures_dir().join("ptr_read_panic.ullbc");
+    assert!(
+        ullbc.exists(),
+        "{}: missing fixture, run `make test-fixtures`",
+        ullbc.display()
+    );
+
+    let inventory: Value = serde_json::from_str(&run_inventory(&ullbc, "json")).unwrap();
+    let totals = &inventory["totals"];
+    assert_eq!(totals["functions"], json!(1));
+    assert_eq!(totals["unsafe_functions"], json!(0));
+    assert_eq!(totals["functions_with_unsafe_operations"], json!(1));
+    assert_eq!(totals["strong_bypasses"]["read_flow"], json!(1));
+    assert_eq!(totals["weak_bypasses"]["write_flow"], json!(1));
+    assert_eq!(totals["unresolved_generic_calls"], json!(1));
+
+    let csv = run_inventory(&ullbc, "csv");
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3, "header, one function and the totals");
+    assert!(lines[0].starts_with("name,is_unsafe,"));
+    assert!(lines[1].starts_with("ptr_read_panic::replace_with,false,"));
+    assert!(lines[2].starts_with("TOTAL,0,"));
+}
+
+#[test]
+fn dot() {
+    let ullbc = fixtures_dir().join("ptr_read_panic.ullbc");
+    assert!(
+        ullbc.exists(),
+        "{}: missing fixture, run `make test-fixtures`",
+        ullbc.display()
+    );
+    let dot_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ptr_read_panic.dot");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-charon-rudra"))
+        .arg("--file")
+        .arg(&ullbc)
+        .arg("--dot")
+        .arg("ptr_read_panic::replace_with")
+        .arg("--dot-output")
+        .arg(&dot_path)
+        .output()
+        .expect("failed to run the analyzer");
+    assert!(
+        output.status.success(),
+        "dot export failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let dot = fs::read_to_string(&dot_path).expect("missing dot file");
+    assert!(dot.starts_with("digraph \"ptr_read_panic::replace_with\" {"));
+    assert!(dot.contains("bb0 ["));
+    // The `ptr::read` block is a source, and is tainted
+    assert!(dot.contains("fillcolor=salmon, color=red"));
+    assert!(dot.trim_end().ends_with('}'));
+}
+
+#[test]
+fn explain() {
+    let ullbc = fixtures_dir().join("ptr_read_panic.ullbc");
+    assert!(
+        ullbc.exists(),
+        "{}: missing fixture, run `make test-fixtures`",
+        ullbc.display()
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-charon-rudra"))
+        .arg("--file")
+        .arg(&ullbc)
+        .arg("--explain")
+        .arg("ptr_read_panic::replace_with")
+        .output()
+        .expect("failed to run the analyzer");
+    assert!(
+        output.status.success(),
+        "explain failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let explanation = String::from_utf8(output.stdout).unwrap();
+    assert!(explanation.starts_with("=== `ptr_read_panic::replace_with` ==="));
+    assert!(explanation.contains("--- Body ---"));
+    assert!(explanation.contains("strong bypass `core::ptr::read`, fn_called_on_copy: false"));
+    assert!(explanation.contains("user closure, sink"));
+    assert!(explanation.contains("bb0: final taint"));
+    assert!(explanation.contains("result: Error UnsafeDataflow:/ReadFlow"));
+}
+
+#[test]
+fn explain_unsafe_only() {
+    let ullbc = fixtures_dir().join("unsafe_only.ullbc");
+    assert!(
+        ullbc.exists(),
+        "{}: missing fixture, run `make test-fixtures`",
+        ullbc.display()
+    );
+
+    let explain = |name: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_cargo-charon-rudra"))
+            .arg("--file")
+            .arg(&ullbc)
+            .arg("--unsafe-only")
+            .arg("--explain")
+            .arg(format!("unsafe_only::{}", name))
+            .output()
+            .expect("failed to run the analyzer");
+        assert!(
+            output.status.success(),
+            "explain failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // The raw pointer is a field of generic type
+    assert!(explain("read_field").contains("result: no report"));
+    // `GlobalAlloc::alloc` is an unsafe trait method
+    assert!(explain("alloc_with").contains("result: no report"));
+    assert!(explain("first_field").contains("--- UnsafeDataflow ---\nnot analyzed"));
+}
+
+#[test]
+fn rendered_report() {
+    let ullbc = fixtures_dir().join("ptr_read_panic.ullbc");
+    assert!(
+        ullbc.exists(),
+        "{}: missing fixture, run `make test-fixtures`",
+        ullbc.display()
+    );
+
+    let reports = run_analyzer(&ullbc);
+    let source = reports["reports"][0]["source"]
+        .as_str()
+        .expect("the report should have a source");
+    // The report file is not colored by default
+    assert!(!source.contains('\u{1b}'));
+
+    // The gutter is aligned on the widest line number
+    let lines: Vec<&str> = source.lines().map(str::trim_start).collect();
+    // The first line of the function, then the labeled lines and their context
+    assert_eq!(lines[0], "|");
+    assert_eq!(
+        lines[1],
+        "6 | pub fn replace_with<T, F: FnOnce(T) -> T>(v: &mut T, f: F) {"
+    );
+    assert!(lines.contains(&"8 |         let old = ptr::read(v);"));
+    assert!(lines.contains(&"9 |         let new = f(old);"));
+    assert!(lines.contains(&"10 |         ptr::write(v, new);"));
+
+    // Each label is underlined below its line
+    let label_line = |message: &str| {
+        lines
+            .iter()
+            .position(|line| line.ends_with(message))
+            .unwrap_or_else(|| panic!("missing label `{}` in:\n{}", message, source))
+    };
+    let read = label_line(" strong lifetime bypass: ptr::read");
+    assert!(lines[read - 1].contains("ptr::read(v)"));
+    assert!(lines[read].contains("^^^"));
+    let closure = label_line(" calls a user-provided closure");
+    assert!(lines[closure - 1].contains("f(old)"));
+    assert!(lines[closure].contains("---"));
+    let write = label_line(" weak lifetime bypass: ptr::write");
+    assert!(lines[write - 1].contains("ptr::write(v, new)"));
+    assert!(lines[write].contains("^^^"));
+}